@@ -0,0 +1,8 @@
+// gRPC code generation of `proto/graph.proto`, see `src/grpc.rs`
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // a vendored `protoc`, so that none is required to be installed
+    std::env::set_var("PROTOC", protoc_bin_vendored::protoc_bin_path()?);
+
+    tonic_build::compile_protos("proto/graph.proto")?;
+    Ok(())
+}
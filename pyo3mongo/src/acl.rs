@@ -0,0 +1,230 @@
+//! Access control
+//!
+//! Teams sharing a database are separated by categories. An `AccessPolicy`
+//! maps API keys to callers and their roles, and roles to the categories they
+//! may access and to what extent: `read` < `write` < `admin`, each level
+//! implying the ones below.
+//!
+//! A policy is a JSON file:
+//!
+//! ```json
+//! {
+//!     "roles": {
+//!         "analyst": [{"categories": ["sales_*"], "operation": "read"}],
+//!         "owner": [{"categories": ["sales_dev"], "operation": "admin"}]
+//!     },
+//!     "keys": {
+//!         "k-123": {"caller": "alice", "roles": ["analyst", "owner"]}
+//!     }
+//! }
+//! ```
+//!
+//! A category pattern is either a category, a prefix ending with `*`, or `*`
+//! for any category. A `GraphService` acting on behalf of a caller (see
+//! `GraphService::with_access`) checks every operation, and denied attempts are
+//! recorded in the `${cat}_audit` collection.
+
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::path::Path;
+use std::str::FromStr;
+
+use mongodb::bson::DateTime;
+use serde::{Deserialize, Serialize};
+
+use crate::{Pyo3MongoError, Pyo3MongoResult};
+
+/// what an operation does to a category, ordered by privilege
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum Operation {
+    /// lookups, traversals & analyses
+    Read,
+    /// creating, updating & deleting vertexes and edges
+    Write,
+    /// indexes, validators, repairs & truncation
+    Admin,
+}
+
+impl FromStr for Operation {
+    type Err = Pyo3MongoError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "read" => Ok(Operation::Read),
+            "write" => Ok(Operation::Write),
+            "admin" => Ok(Operation::Admin),
+            _ => Err(Pyo3MongoError::Config(format!("unknown operation: {}", s))),
+        }
+    }
+}
+
+impl Display for Operation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            Operation::Read => "read",
+            Operation::Write => "write",
+            Operation::Admin => "admin",
+        };
+        f.write_str(s)
+    }
+}
+
+/// categories matching any of the patterns, up to `operation`
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Grant {
+    pub categories: Vec<String>,
+    pub operation: Operation,
+}
+
+impl Grant {
+    pub fn allows(&self, cat: &str, op: Operation) -> bool {
+        op <= self.operation && self.categories.iter().any(|p| matches_category(p, cat))
+    }
+}
+
+fn matches_category(pattern: &str, cat: &str) -> bool {
+    match pattern.strip_suffix('*') {
+        Some(prefix) => cat.starts_with(prefix),
+        None => pattern == cat,
+    }
+}
+
+/// the caller an API key stands for, and its roles
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ApiKey {
+    pub caller: String,
+    pub roles: Vec<String>,
+}
+
+/// roles by name, and API keys
+#[derive(Serialize, Deserialize, Default, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct AccessPolicy {
+    pub roles: HashMap<String, Vec<Grant>>,
+    pub keys: HashMap<String, ApiKey>,
+}
+
+impl AccessPolicy {
+    /// read a JSON policy file, roles of every key must be defined
+    pub fn from_file(path: impl AsRef<Path>) -> Pyo3MongoResult<Self> {
+        let content = std::fs::read_to_string(path)?;
+        let policy: Self = serde_json::from_str(&content)?;
+        policy.check()?;
+        Ok(policy)
+    }
+
+    pub fn check(&self) -> Pyo3MongoResult<()> {
+        for key in self.keys.values() {
+            if let Some(role) = key.roles.iter().find(|r| !self.roles.contains_key(*r)) {
+                return Err(Pyo3MongoError::Config(format!(
+                    "unknown role {} of caller {}",
+                    role, key.caller
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// the caller of an API key, with the grants of all its roles
+    pub fn principal(&self, api_key: &str) -> Option<Principal> {
+        let key = self.keys.get(api_key)?;
+        let grants = key
+            .roles
+            .iter()
+            .filter_map(|r| self.roles.get(r))
+            .flatten()
+            .cloned()
+            .collect();
+
+        Some(Principal {
+            caller: key.caller.clone(),
+            grants,
+        })
+    }
+}
+
+/// an authenticated caller
+#[derive(Clone, Debug, PartialEq)]
+pub struct Principal {
+    pub caller: String,
+    pub grants: Vec<Grant>,
+}
+
+impl Principal {
+    pub fn allows(&self, cat: &str, op: Operation) -> bool {
+        self.grants.iter().any(|g| g.allows(cat, op))
+    }
+}
+
+/// a denied attempt, as stored in `${cat}_audit`
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct AuditRecord {
+    pub at: DateTime,
+    /// `None` if the API key is unknown
+    pub caller: Option<String>,
+    pub category: String,
+    pub operation: Operation,
+    /// name of the denied method
+    pub action: String,
+}
+
+#[cfg(test)]
+mod test_acl {
+    use super::*;
+
+    fn policy() -> AccessPolicy {
+        serde_json::from_str(
+            r#"{
+                "roles": {
+                    "analyst": [{"categories": ["sales_*"], "operation": "read"}],
+                    "owner": [{"categories": ["sales_dev"], "operation": "admin"}]
+                },
+                "keys": {
+                    "k-1": {"caller": "alice", "roles": ["analyst", "owner"]},
+                    "k-2": {"caller": "bob", "roles": ["analyst"]}
+                }
+            }"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_principal() {
+        let policy = policy();
+        policy.check().unwrap();
+        assert!(policy.principal("k-0").is_none());
+
+        let alice = policy.principal("k-1").unwrap();
+        assert!(alice.allows("sales_prod", Operation::Read));
+        assert!(!alice.allows("sales_prod", Operation::Write));
+        assert!(alice.allows("sales_dev", Operation::Admin));
+        assert!(!alice.allows("hr", Operation::Read));
+
+        let bob = policy.principal("k-2").unwrap();
+        assert_eq!(bob.caller, "bob");
+        assert!(!bob.allows("sales_dev", Operation::Write));
+    }
+
+    #[test]
+    fn test_check() {
+        let mut policy = policy();
+        policy.keys.insert(
+            "k-3".to_owned(),
+            ApiKey {
+                caller: "carol".to_owned(),
+                roles: vec!["auditor".to_owned()],
+            },
+        );
+        assert!(policy.check().is_err());
+    }
+
+    #[test]
+    fn test_matches_category() {
+        assert!(matches_category("*", "anything"));
+        assert!(matches_category("team_*", "team_a"));
+        assert!(!matches_category("team_*", "other"));
+        assert!(matches_category("team_a", "team_a"));
+        assert!(!matches_category("team_a", "team_ab"));
+    }
+}
@@ -0,0 +1,48 @@
+use clap::Parser;
+use p3m::telemetry;
+use p3m::{GraphService, MongoConfig, Pyo3MongoResult, RepairAction};
+
+#[derive(Parser, Debug)]
+#[clap(about, version, author)]
+struct Args {
+    /// JSON config file, `MONGO_*` environment variables are read if absent
+    #[clap(long)]
+    config: Option<String>,
+
+    #[clap(short, long)]
+    uri: Option<String>,
+
+    #[clap(short, long)]
+    database: Option<String>,
+
+    #[clap(short, long, default_value = "dev")]
+    category: String,
+
+    /// repair offenders, otherwise only report them (dry run)
+    #[clap(long)]
+    repair: bool,
+
+    /// move offenders into quarantine collections instead of deleting them
+    #[clap(long)]
+    quarantine: bool,
+}
+
+#[tokio::main]
+async fn main() -> Pyo3MongoResult<()> {
+    let args = Args::parse();
+    telemetry::init_tracing("p3m=warn");
+
+    let config = MongoConfig::load(args.config.as_deref())?.overrides(args.uri, args.database);
+    let gs = GraphService::with_config(&config, &args.category).await?;
+
+    let action = if args.quarantine {
+        RepairAction::Quarantine
+    } else {
+        RepairAction::Delete
+    };
+    let report = gs.repair(action, !args.repair).await?;
+
+    println!("{}", serde_json::to_string_pretty(&report)?);
+
+    Ok(())
+}
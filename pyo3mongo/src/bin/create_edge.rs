@@ -2,23 +2,30 @@ use std::str::FromStr;
 
 use bson::oid::ObjectId;
 use clap::Parser;
-use p3m::{EdgeDto, GraphService, Pyo3MongoResult};
+use p3m::telemetry;
+use p3m::{EdgeDto, GraphService, MongoConfig, Pyo3MongoResult};
 
 #[derive(Parser, Debug)]
 #[clap(about, version, author)]
 struct Args {
+    /// JSON config file, `MONGO_*` environment variables are read if absent
+    #[clap(long)]
+    config: Option<String>,
+
     #[clap(short, long)]
-    uri: String,
+    uri: Option<String>,
 
-    #[clap(short, long, default_value = "graph")]
-    database: String,
+    #[clap(short, long)]
+    database: Option<String>,
 
     #[clap(short, long, default_value = "dev")]
     category: String,
 
+    /// id of the source vertex, or its name with `--by-name`
     #[clap(long)]
     source: String,
 
+    /// id of the target vertex, or its name with `--by-name`
     #[clap(long)]
     target: String,
 
@@ -27,16 +34,30 @@ struct Args {
 
     #[clap(long)]
     label: Option<String>,
+
+    /// look up `source` & `target` by vertex name
+    #[clap(long)]
+    by_name: bool,
 }
 
 #[tokio::main]
 async fn main() -> Pyo3MongoResult<()> {
     let args = Args::parse();
+    telemetry::init_tracing("p3m=warn");
 
-    let gs = GraphService::new(&args.uri, &args.database, &args.category).await?;
+    let config = MongoConfig::load(args.config.as_deref())?.overrides(args.uri, args.database);
+    let gs = GraphService::with_config(&config, &args.category).await?;
 
-    let source = ObjectId::from_str(&args.source)?;
-    let target = ObjectId::from_str(&args.target)?;
+    let (source, target) = if args.by_name {
+        let source = gs.get_vertex_by_name(&args.source).await?;
+        let target = gs.get_vertex_by_name(&args.target).await?;
+        (source.id.unwrap(), target.id.unwrap())
+    } else {
+        (
+            ObjectId::from_str(&args.source)?,
+            ObjectId::from_str(&args.target)?,
+        )
+    };
     let weight = args.weight;
     let label = args.label;
     let dto = EdgeDto::new(source, target, weight, label.as_deref());
@@ -1,29 +1,45 @@
 use clap::Parser;
-use p3m::{GraphService, Pyo3MongoResult, VertexDto};
+use p3m::telemetry;
+use p3m::{GraphService, MongoConfig, Pyo3MongoResult, VertexDto};
 
 #[derive(Parser, Debug)]
 #[clap(about, version, author)]
 struct Args {
+    /// JSON config file, `MONGO_*` environment variables are read if absent
+    #[clap(long)]
+    config: Option<String>,
+
     #[clap(short, long)]
-    uri: String,
+    uri: Option<String>,
 
-    #[clap(short, long, default_value = "graph")]
-    database: String,
+    #[clap(short, long)]
+    database: Option<String>,
 
     #[clap(short, long, default_value = "dev")]
     category: String,
 
     #[clap(long)]
     name: String,
+
+    /// return the vertex of the same name if there is one
+    #[clap(long)]
+    upsert: bool,
 }
 
 #[tokio::main]
 async fn main() -> Pyo3MongoResult<()> {
     let args = Args::parse();
+    telemetry::init_tracing("p3m=warn");
 
-    let gs = GraphService::new(&args.uri, &args.database, &args.category).await?;
+    let config = MongoConfig::load(args.config.as_deref())?.overrides(args.uri, args.database);
+    let gs = GraphService::with_config(&config, &args.category).await?;
 
-    let vertex = gs.create_vertex(VertexDto::new(&args.name)).await?;
+    let dto = VertexDto::new(&args.name);
+    let vertex = if args.upsert {
+        gs.get_or_create_vertex(dto).await?
+    } else {
+        gs.create_vertex(dto).await?
+    };
 
     println!("{:?}", vertex);
 
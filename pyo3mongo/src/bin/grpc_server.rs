@@ -0,0 +1,86 @@
+//! gRPC server of a category, see `proto/graph.proto` for the contract
+
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use axum::http::header;
+use axum::routing::get;
+use axum::Router;
+use clap::Parser;
+use p3m::grpc::GraphRpc;
+use p3m::telemetry;
+use p3m::{GraphService, GraphStore, MemoryStore, MongoConfig, Pyo3MongoError, Pyo3MongoResult};
+
+#[derive(Parser, Debug)]
+#[clap(about, version, author)]
+struct Args {
+    /// JSON config file, `MONGO_*` environment variables are read if absent
+    #[clap(long)]
+    config: Option<String>,
+
+    #[clap(short, long)]
+    uri: Option<String>,
+
+    #[clap(short, long)]
+    database: Option<String>,
+
+    #[clap(short, long, default_value = "dev")]
+    category: String,
+
+    #[clap(long, default_value = "127.0.0.1:50051")]
+    addr: String,
+
+    /// serve metrics in the Prometheus text format on `http://{addr}/metrics`
+    #[clap(long)]
+    metrics_addr: Option<String>,
+
+    /// serve an in-memory store instead of MongoDB, lost on exit
+    #[clap(long)]
+    memory: bool,
+}
+
+#[tokio::main]
+async fn main() -> Pyo3MongoResult<()> {
+    let args = Args::parse();
+    telemetry::init_tracing("p3m=info");
+
+    let addr = args
+        .addr
+        .parse::<SocketAddr>()
+        .map_err(|_| Pyo3MongoError::Common("invalid address"))?;
+
+    let store: Arc<dyn GraphStore> = if args.memory {
+        Arc::new(MemoryStore::new())
+    } else {
+        let config = MongoConfig::load(args.config.as_deref())?.overrides(args.uri, args.database);
+        Arc::new(GraphService::with_config(&config, &args.category).await?)
+    };
+
+    if let Some(metrics_addr) = &args.metrics_addr {
+        let listener = tokio::net::TcpListener::bind(metrics_addr).await?;
+        let router = Router::new().route(
+            "/metrics",
+            get(|| async {
+                (
+                    [(header::CONTENT_TYPE, telemetry::CONTENT_TYPE)],
+                    telemetry::render(),
+                )
+            }),
+        );
+        // dropped along with the runtime
+        tokio::spawn(async move { axum::serve(listener, router).await });
+        println!("metrics on http://{}/metrics", metrics_addr);
+    }
+
+    println!("serving {} on {}", args.category, addr);
+
+    // in-flight calls are completed before exiting
+    tonic::transport::Server::builder()
+        .add_service(GraphRpc::new(store).into_server())
+        .serve_with_shutdown(addr, p3m::shutdown_signal())
+        .await?;
+
+    println!("shut down");
+
+    Ok(())
+}
@@ -0,0 +1,69 @@
+//! HTTP server of a category, see `routes` for the endpoints
+
+mod openapi;
+mod routes;
+
+use std::sync::Arc;
+
+use clap::Parser;
+use p3m::telemetry;
+use p3m::{GraphService, MemoryStore, MongoConfig, Pyo3MongoResult};
+use routes::Store;
+
+#[derive(Parser, Debug)]
+#[clap(about, version, author)]
+struct Args {
+    /// JSON config file, `MONGO_*` environment variables are read if absent
+    #[clap(long)]
+    config: Option<String>,
+
+    #[clap(short, long)]
+    uri: Option<String>,
+
+    #[clap(short, long)]
+    database: Option<String>,
+
+    #[clap(short, long, default_value = "dev")]
+    category: String,
+
+    #[clap(long, default_value = "127.0.0.1:8080")]
+    addr: String,
+
+    /// serve an in-memory store instead of MongoDB, lost on exit
+    #[clap(long)]
+    memory: bool,
+
+    /// print the OpenAPI document and exit
+    #[clap(long)]
+    openapi: bool,
+}
+
+#[tokio::main]
+async fn main() -> Pyo3MongoResult<()> {
+    let args = Args::parse();
+    telemetry::init_tracing("p3m=info");
+
+    if args.openapi {
+        println!("{:#}", openapi::document());
+        return Ok(());
+    }
+
+    let store: Store = if args.memory {
+        Arc::new(MemoryStore::new())
+    } else {
+        let config = MongoConfig::load(args.config.as_deref())?.overrides(args.uri, args.database);
+        Arc::new(GraphService::with_config(&config, &args.category).await?)
+    };
+
+    let listener = tokio::net::TcpListener::bind(&args.addr).await?;
+    println!("serving {} on http://{}", args.category, args.addr);
+
+    // in-flight requests are completed before exiting
+    axum::serve(listener, routes::router(store))
+        .with_graceful_shutdown(p3m::shutdown_signal())
+        .await?;
+
+    println!("shut down");
+
+    Ok(())
+}
@@ -0,0 +1,221 @@
+//! OpenAPI document of the endpoints in `routes`
+
+use serde_json::{json, Value};
+
+fn schema(name: &str) -> Value {
+    json!({"$ref": format!("#/components/schemas/{}", name)})
+}
+
+fn array_of(name: &str) -> Value {
+    json!({"type": "array", "items": schema(name)})
+}
+
+fn content(schema: Value) -> Value {
+    json!({"application/json": {"schema": schema}})
+}
+
+fn response(description: &str, schema: Value) -> Value {
+    json!({"description": description, "content": content(schema)})
+}
+
+fn body(name: &str) -> Value {
+    json!({"required": true, "content": content(schema(name))})
+}
+
+fn query(name: &str, ty: &str, description: &str) -> Value {
+    json!({
+        "name": name,
+        "in": "query",
+        "required": false,
+        "schema": {"type": ty},
+        "description": description,
+    })
+}
+
+fn errors() -> Value {
+    json!({
+        "400": response("invalid request", schema("Error")),
+        "404": response("not found", schema("Error")),
+    })
+}
+
+// an operation, along with the error responses
+fn operation(summary: &str, ok: (&str, Value)) -> Value {
+    let mut responses = errors();
+    responses[ok.0] = ok.1;
+    json!({"summary": summary, "responses": responses})
+}
+
+fn with(mut op: Value, key: &str, value: Value) -> Value {
+    op[key] = value;
+    op
+}
+
+fn paths() -> Value {
+    let id = json!([{"name": "id", "in": "path", "required": true, "schema": {"type": "string"}}]);
+    let traversal = json!([
+        query("label", "string", "only edges of the label"),
+        query("depth", "integer", "maximum depth, unlimited if absent"),
+        query("at", "string", "only edges valid at this RFC 3339 instant"),
+    ]);
+    let no_content = json!({"description": "deleted"});
+
+    json!({
+        "/vertexes": {
+            "get": with(
+                operation("all vertexes, or the one named `name`", ("200", response("vertexes", array_of("Vertex")))),
+                "parameters",
+                json!([query("name", "string", "vertex name")]),
+            ),
+            "post": with(
+                operation("create a vertex", ("201", response("created", schema("Vertex")))),
+                "requestBody",
+                body("VertexInput"),
+            ),
+        },
+        "/vertexes/{id}": {
+            "parameters": id,
+            "get": operation("a vertex", ("200", response("vertex", schema("Vertex")))),
+            "put": with(
+                operation("rename a vertex", ("200", response("updated", schema("Vertex")))),
+                "requestBody",
+                body("VertexInput"),
+            ),
+            "delete": operation("delete a vertex and its edges", ("204", no_content.clone())),
+        },
+        "/vertexes/{id}/edges": {
+            "parameters": id,
+            "get": with(
+                operation("edges of a vertex", ("200", response("edges", array_of("Edge")))),
+                "parameters",
+                json!([query("direction", "string", "\"out\", \"in\" or \"both\" (by default)")]),
+            ),
+        },
+        "/vertexes/{id}/graph": {
+            "parameters": id,
+            "get": with(
+                operation("edges reachable from a vertex, and their targets", ("200", response("graph", schema("Graph")))),
+                "parameters",
+                traversal,
+            ),
+        },
+        "/edges": {
+            "get": operation("all edges", ("200", response("edges", array_of("Edge")))),
+            "post": with(
+                operation("create an edge", ("201", response("created", schema("Edge")))),
+                "requestBody",
+                body("Edge"),
+            ),
+        },
+        "/edges/{id}": {
+            "parameters": id,
+            "get": operation("an edge", ("200", response("edge", schema("Edge")))),
+            "put": with(
+                operation("replace an edge", ("200", response("updated", schema("Edge")))),
+                "requestBody",
+                body("Edge"),
+            ),
+            "delete": operation("delete an edge", ("204", no_content)),
+        },
+        "/graph": {
+            "get": operation("export the whole category", ("200", response("graph", schema("Graph")))),
+            "post": with(
+                operation("import vertexes & edges in bulk", ("201", response("inserted", schema("Graph")))),
+                "requestBody",
+                body("Graph"),
+            ),
+        },
+        "/stats": {
+            "get": operation("statistics of the category", ("200", response("statistics", schema("Stats")))),
+        },
+        "/metrics": {
+            "get": {
+                "summary": "metrics of graph operations",
+                "responses": {"200": {
+                    "description": "Prometheus text format",
+                    "content": {"text/plain": {"schema": {"type": "string"}}},
+                }},
+            },
+        },
+        "/openapi.json": {
+            "get": {
+                "summary": "this document",
+                "responses": {"200": {"description": "OpenAPI document"}},
+            },
+        },
+    })
+}
+
+fn schemas() -> Value {
+    let string = json!({"type": "string"});
+    let datetime = json!({"type": "string", "format": "date-time", "nullable": true});
+    let count = json!({"type": "integer", "minimum": 0});
+
+    json!({
+        "Vertex": {
+            "type": "object",
+            "required": ["name"],
+            "properties": {
+                "id": string,
+                "name": string,
+                "properties": {"type": "object", "description": "relaxed extended JSON"},
+            },
+        },
+        "VertexInput": {
+            "type": "object",
+            "required": ["name"],
+            "properties": {"name": string},
+        },
+        "Edge": {
+            "type": "object",
+            "required": ["source", "target"],
+            "properties": {
+                "id": string,
+                "source": string,
+                "target": string,
+                "weight": {"type": "number", "nullable": true},
+                "label": {"type": "string", "nullable": true},
+                "valid_from": datetime,
+                "valid_to": datetime,
+            },
+        },
+        "Graph": {
+            "type": "object",
+            "properties": {
+                "vertexes": array_of("Vertex"),
+                "edges": array_of("Edge"),
+            },
+        },
+        "Stats": {
+            "type": "object",
+            "properties": {
+                "vertex_count": count,
+                "edge_count": count,
+                "labels": {"type": "object", "additionalProperties": count},
+                "unlabeled_edge_count": count,
+                "average_degree": {"type": "number"},
+                "orphan_vertex_count": count,
+                "dangling_edge_count": count,
+                "indexes": {"type": "array", "items": {"type": "object"}},
+            },
+        },
+        "Error": {
+            "type": "object",
+            "required": ["error"],
+            "properties": {"error": string},
+        },
+    })
+}
+
+pub fn document() -> Value {
+    json!({
+        "openapi": "3.0.3",
+        "info": {
+            "title": "pyo3mongo",
+            "version": env!("CARGO_PKG_VERSION"),
+            "description": "vertexes & edges of a category",
+        },
+        "paths": paths(),
+        "components": {"schemas": schemas()},
+    })
+}
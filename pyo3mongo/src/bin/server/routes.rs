@@ -0,0 +1,616 @@
+//! JSON endpoints over any `GraphStore`
+//!
+//! Ids are hex strings and datetimes are RFC 3339 strings, vertex properties
+//! are relaxed extended JSON.
+
+use std::str::FromStr;
+use std::sync::Arc;
+
+use axum::extract::{Path, Query, State};
+use axum::http::{header, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::{Json, Router};
+use bson::oid::ObjectId;
+use bson::{Bson, DateTime};
+use p3m::{retry, telemetry};
+use p3m::{
+    Edge, EdgeDto, FindEdgeByVertexDto, GraphStats, GraphStore, Pyo3MongoError, Pyo3MongoResult,
+    Vertex, VertexDto,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::openapi;
+
+pub type Store = Arc<dyn GraphStore>;
+
+pub fn router(store: Store) -> Router {
+    Router::new()
+        .route("/vertexes", get(list_vertexes).post(create_vertex))
+        .route(
+            "/vertexes/:id",
+            get(get_vertex).put(update_vertex).delete(delete_vertex),
+        )
+        .route("/vertexes/:id/edges", get(get_vertex_edges))
+        .route("/vertexes/:id/graph", get(get_vertex_graph))
+        .route("/edges", get(list_edges).post(create_edge))
+        .route(
+            "/edges/:id",
+            get(get_edge).put(update_edge).delete(delete_edge),
+        )
+        .route("/graph", get(export_graph).post(import_graph))
+        .route("/stats", get(stats))
+        .route("/metrics", get(metrics))
+        .route("/openapi.json", get(|| async { Json(openapi::document()) }))
+        .with_state(store)
+}
+
+/// a store error as a response, `{"error": message}`
+pub struct ApiError(Pyo3MongoError);
+
+impl<E: Into<Pyo3MongoError>> From<E> for ApiError {
+    fn from(e: E) -> Self {
+        ApiError(e.into())
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let status = status(&self.0);
+        (status, Json(json!({"error": self.0.to_string()}))).into_response()
+    }
+}
+
+// a retried error is mapped as its source, a transient one as unavailable
+fn status(e: &Pyo3MongoError) -> StatusCode {
+    match e {
+        Pyo3MongoError::VertexNotFound
+        | Pyo3MongoError::EdgeNotFound
+        | Pyo3MongoError::HyperEdgeNotFound => StatusCode::NOT_FOUND,
+        Pyo3MongoError::Common(_)
+        | Pyo3MongoError::Query(_)
+        | Pyo3MongoError::Oid(_)
+        | Pyo3MongoError::De(_) => StatusCode::BAD_REQUEST,
+        Pyo3MongoError::Conflict(_) | Pyo3MongoError::Write(_) => StatusCode::CONFLICT,
+        Pyo3MongoError::Denied(_) => StatusCode::FORBIDDEN,
+        Pyo3MongoError::Retried { source, .. } => status(source),
+        e if retry::is_transient(e) => StatusCode::SERVICE_UNAVAILABLE,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+type ApiResult<T> = Result<T, ApiError>;
+
+fn oid(hex: &str) -> Pyo3MongoResult<ObjectId> {
+    Ok(ObjectId::from_str(hex)?)
+}
+
+fn datetime(s: &Option<String>) -> Pyo3MongoResult<Option<DateTime>> {
+    s.as_deref()
+        .map(|s| {
+            DateTime::parse_rfc3339_str(s).map_err(|_| Pyo3MongoError::Common("invalid datetime"))
+        })
+        .transpose()
+}
+
+fn rfc3339(dt: Option<DateTime>) -> Option<String> {
+    dt.and_then(|dt| dt.try_to_rfc3339_string().ok())
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct VertexJson {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub properties: Option<Value>,
+}
+
+impl From<Vertex> for VertexJson {
+    fn from(v: Vertex) -> Self {
+        VertexJson {
+            id: v.id.map(|id| id.to_hex()),
+            name: v.name,
+            properties: v
+                .properties
+                .map(|p| Bson::Document(p).into_relaxed_extjson()),
+        }
+    }
+}
+
+impl TryFrom<VertexJson> for Vertex {
+    type Error = Pyo3MongoError;
+
+    fn try_from(v: VertexJson) -> Result<Self, Self::Error> {
+        let properties = match v.properties.map(Bson::try_from) {
+            Some(Ok(Bson::Document(d))) => Some(d),
+            Some(_) => return Err(Pyo3MongoError::Common("properties must be an object")),
+            None => None,
+        };
+        Ok(Vertex {
+            id: v.id.as_deref().map(oid).transpose()?,
+            name: v.name,
+            properties,
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct EdgeJson {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    pub source: String,
+    pub target: String,
+    #[serde(default)]
+    pub weight: Option<f64>,
+    #[serde(default)]
+    pub label: Option<String>,
+    #[serde(default)]
+    pub valid_from: Option<String>,
+    #[serde(default)]
+    pub valid_to: Option<String>,
+}
+
+impl From<Edge> for EdgeJson {
+    fn from(e: Edge) -> Self {
+        EdgeJson {
+            id: e.id.map(|id| id.to_hex()),
+            source: e.source.to_hex(),
+            target: e.target.to_hex(),
+            weight: e.weight,
+            label: e.label,
+            valid_from: rfc3339(e.valid_from),
+            valid_to: rfc3339(e.valid_to),
+        }
+    }
+}
+
+impl EdgeJson {
+    fn dto(&self) -> Pyo3MongoResult<EdgeDto<'_>> {
+        let dto = EdgeDto::new(
+            oid(&self.source)?,
+            oid(&self.target)?,
+            self.weight,
+            self.label.as_deref(),
+        );
+        Ok(dto.valid_between(datetime(&self.valid_from)?, datetime(&self.valid_to)?))
+    }
+}
+
+impl TryFrom<EdgeJson> for Edge {
+    type Error = Pyo3MongoError;
+
+    fn try_from(e: EdgeJson) -> Result<Self, Self::Error> {
+        Ok(Edge {
+            id: e.id.as_deref().map(oid).transpose()?,
+            ..Edge::from(e.dto()?)
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize, Default, Debug)]
+pub struct GraphJson {
+    #[serde(default)]
+    pub vertexes: Vec<VertexJson>,
+    #[serde(default)]
+    pub edges: Vec<EdgeJson>,
+}
+
+impl GraphJson {
+    fn new(vertexes: Vec<Vertex>, edges: Vec<Edge>) -> Self {
+        GraphJson {
+            vertexes: vertexes.into_iter().map(VertexJson::from).collect(),
+            edges: edges.into_iter().map(EdgeJson::from).collect(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct VertexInput {
+    pub name: String,
+}
+
+#[derive(Deserialize)]
+pub struct NameQuery {
+    name: Option<String>,
+}
+
+/// all vertexes, or the one named `name`
+async fn list_vertexes(
+    State(store): State<Store>,
+    Query(q): Query<NameQuery>,
+) -> ApiResult<Json<Vec<VertexJson>>> {
+    let vertexes = match q.name {
+        Some(name) => match store.get_vertex_by_name(&name).await {
+            Ok(v) => vec![v],
+            Err(Pyo3MongoError::VertexNotFound) => vec![],
+            Err(e) => return Err(e.into()),
+        },
+        None => store.get_all_vertexes().await?,
+    };
+
+    Ok(Json(vertexes.into_iter().map(VertexJson::from).collect()))
+}
+
+async fn create_vertex(
+    State(store): State<Store>,
+    Json(input): Json<VertexInput>,
+) -> ApiResult<(StatusCode, Json<VertexJson>)> {
+    let vertex = store.create_vertex(VertexDto::new(&input.name)).await?;
+
+    Ok((StatusCode::CREATED, Json(vertex.into())))
+}
+
+async fn get_vertex(
+    State(store): State<Store>,
+    Path(id): Path<String>,
+) -> ApiResult<Json<VertexJson>> {
+    let vertex = store.get_vertex(oid(&id)?).await?;
+
+    Ok(Json(vertex.into()))
+}
+
+/// responds with the vertex after update
+async fn update_vertex(
+    State(store): State<Store>,
+    Path(id): Path<String>,
+    Json(input): Json<VertexInput>,
+) -> ApiResult<Json<VertexJson>> {
+    let id = oid(&id)?;
+    store.update_vertex(id, VertexDto::new(&input.name)).await?;
+
+    Ok(Json(store.get_vertex(id).await?.into()))
+}
+
+async fn delete_vertex(
+    State(store): State<Store>,
+    Path(id): Path<String>,
+) -> ApiResult<StatusCode> {
+    store.delete_vertex(oid(&id)?).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[derive(Deserialize)]
+pub struct DirectionQuery {
+    /// "out", "in" or "both" (by default)
+    direction: Option<String>,
+}
+
+async fn get_vertex_edges(
+    State(store): State<Store>,
+    Path(id): Path<String>,
+    Query(q): Query<DirectionQuery>,
+) -> ApiResult<Json<Vec<EdgeJson>>> {
+    let id = oid(&id)?;
+    let find = match q.direction.as_deref() {
+        Some("out") => FindEdgeByVertexDto::Source(id),
+        Some("in") => FindEdgeByVertexDto::Target(id),
+        None | Some("both") => FindEdgeByVertexDto::Bidirectional(id),
+        Some(_) => return Err(Pyo3MongoError::Common("unknown direction").into()),
+    };
+    let edges = store.get_edges_by_vertex(find).await?;
+
+    Ok(Json(edges.into_iter().map(EdgeJson::from).collect()))
+}
+
+#[derive(Deserialize)]
+pub struct TraversalQuery {
+    label: Option<String>,
+    depth: Option<i32>,
+    at: Option<String>,
+}
+
+/// edges reachable from the vertex, and their targets
+async fn get_vertex_graph(
+    State(store): State<Store>,
+    Path(id): Path<String>,
+    Query(q): Query<TraversalQuery>,
+) -> ApiResult<Json<GraphJson>> {
+    let (edges, vertexes) = store
+        .get_graph_from_vertex_by_label(oid(&id)?, q.label.as_deref(), q.depth, datetime(&q.at)?)
+        .await?;
+
+    Ok(Json(GraphJson::new(vertexes, edges)))
+}
+
+async fn list_edges(State(store): State<Store>) -> ApiResult<Json<Vec<EdgeJson>>> {
+    let edges = store.get_all_edges().await?;
+
+    Ok(Json(edges.into_iter().map(EdgeJson::from).collect()))
+}
+
+async fn create_edge(
+    State(store): State<Store>,
+    Json(input): Json<EdgeJson>,
+) -> ApiResult<(StatusCode, Json<EdgeJson>)> {
+    let edge = store.create_edge(input.dto()?).await?;
+
+    Ok((StatusCode::CREATED, Json(edge.into())))
+}
+
+async fn get_edge(State(store): State<Store>, Path(id): Path<String>) -> ApiResult<Json<EdgeJson>> {
+    let edge = store.get_edge(oid(&id)?).await?;
+
+    Ok(Json(edge.into()))
+}
+
+/// responds with the edge after update
+async fn update_edge(
+    State(store): State<Store>,
+    Path(id): Path<String>,
+    Json(input): Json<EdgeJson>,
+) -> ApiResult<Json<EdgeJson>> {
+    let id = oid(&id)?;
+    store.update_edge(id, input.dto()?).await?;
+
+    Ok(Json(store.get_edge(id).await?.into()))
+}
+
+async fn delete_edge(State(store): State<Store>, Path(id): Path<String>) -> ApiResult<StatusCode> {
+    store.delete_edge(oid(&id)?).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// the whole category
+async fn export_graph(State(store): State<Store>) -> ApiResult<Json<GraphJson>> {
+    let vertexes = store.get_all_vertexes().await?;
+    let edges = store.get_all_edges().await?;
+
+    Ok(Json(GraphJson::new(vertexes, edges)))
+}
+
+/// bulk insert, responds with what has been inserted (along with the ids)
+async fn import_graph(
+    State(store): State<Store>,
+    Json(input): Json<GraphJson>,
+) -> ApiResult<(StatusCode, Json<GraphJson>)> {
+    let vertexes = input
+        .vertexes
+        .into_iter()
+        .map(Vertex::try_from)
+        .collect::<Pyo3MongoResult<Vec<_>>>()?;
+    let edges = input
+        .edges
+        .into_iter()
+        .map(Edge::try_from)
+        .collect::<Pyo3MongoResult<Vec<_>>>()?;
+    let (edges, vertexes) = store.insert_graph(vertexes, edges).await?;
+
+    Ok((StatusCode::CREATED, Json(GraphJson::new(vertexes, edges))))
+}
+
+async fn stats(State(store): State<Store>) -> ApiResult<Json<GraphStats>> {
+    Ok(Json(store.stats().await?))
+}
+
+async fn metrics() -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, telemetry::CONTENT_TYPE)],
+        telemetry::render(),
+    )
+}
+
+#[cfg(test)]
+mod test_routes {
+    use axum::body::{to_bytes, Body};
+    use axum::http::{Method, Request};
+    use p3m::harness::TestGraph;
+    use p3m::MemoryStore;
+    use tower::ServiceExt;
+
+    use super::*;
+
+    async fn call(
+        app: &Router,
+        method: Method,
+        uri: &str,
+        body: Option<Value>,
+    ) -> (StatusCode, Value) {
+        let request = Request::builder()
+            .method(method)
+            .uri(uri)
+            .header("content-type", "application/json")
+            .body(body.map_or_else(Body::empty, |b| Body::from(b.to_string())))
+            .unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+
+        let status = response.status();
+        let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let value = serde_json::from_slice(&bytes).unwrap_or(Value::Null);
+        (status, value)
+    }
+
+    fn app() -> Router {
+        router(Arc::new(MemoryStore::new()))
+    }
+
+    // a `MemoryStore`, and a service over a `TestGraph` if a test server is
+    // set, for the endpoints to behave the same on both. The `TestGraph` is
+    // kept along, its category is dropped with it.
+    async fn stores(name: &str) -> Vec<(Store, Option<TestGraph>)> {
+        let mut stores: Vec<(Store, Option<TestGraph>)> =
+            vec![(Arc::new(MemoryStore::new()), None)];
+        if let Some(graph) = TestGraph::connect(name).await {
+            let service = graph.service().await.unwrap();
+            stores.push((Arc::new(service), Some(graph)));
+        }
+        stores
+    }
+
+    #[tokio::test]
+    async fn test_vertex_crud() {
+        for (store, _graph) in stores("vertex_crud").await {
+            vertex_crud(&router(store)).await;
+        }
+    }
+
+    async fn vertex_crud(app: &Router) {
+        let (status, v) = call(app, Method::POST, "/vertexes", Some(json!({"name": "a"}))).await;
+        assert_eq!(status, StatusCode::CREATED);
+        let uri = format!("/vertexes/{}", v["id"].as_str().unwrap());
+
+        let (status, got) = call(app, Method::GET, &uri, None).await;
+        assert_eq!((status, &got), (StatusCode::OK, &v));
+
+        let (_, got) = call(app, Method::PUT, &uri, Some(json!({"name": "b"}))).await;
+        assert_eq!(got["name"], "b");
+        let (_, found) = call(app, Method::GET, "/vertexes?name=b", None).await;
+        assert_eq!(found.as_array().unwrap().len(), 1);
+
+        let (status, _) = call(app, Method::DELETE, &uri, None).await;
+        assert_eq!(status, StatusCode::NO_CONTENT);
+        let (status, err) = call(app, Method::GET, &uri, None).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert!(err["error"].is_string());
+
+        let (status, _) = call(app, Method::GET, "/vertexes/not-an-id", None).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_edges_and_traversal() {
+        for (store, _graph) in stores("edges_and_traversal").await {
+            edges_and_traversal(&router(store)).await;
+        }
+    }
+
+    async fn edges_and_traversal(app: &Router) {
+        let mut ids = Vec::new();
+        for name in ["a", "b", "c"] {
+            let (_, v) = call(app, Method::POST, "/vertexes", Some(json!({"name": name}))).await;
+            ids.push(v["id"].as_str().unwrap().to_owned());
+        }
+
+        // a -> b -> c, the latter only valid since 2022
+        let (status, ab) = call(
+            app,
+            Method::POST,
+            "/edges",
+            Some(json!({"source": ids[0], "target": ids[1], "label": "l"})),
+        )
+        .await;
+        assert_eq!(status, StatusCode::CREATED);
+        let since = "2022-01-01T00:00:00Z";
+        let (_, bc) = call(
+            app,
+            Method::POST,
+            "/edges",
+            Some(json!({"source": ids[1], "target": ids[2], "label": "l", "valid_from": since})),
+        )
+        .await;
+        assert_eq!(bc["valid_from"], since);
+
+        let uri = format!("/vertexes/{}/graph?label=l", ids[0]);
+        let (_, graph) = call(app, Method::GET, &uri, None).await;
+        assert_eq!(graph["edges"].as_array().unwrap().len(), 2);
+        let uri = format!("/vertexes/{}/graph?at=2021-01-01T00:00:00Z", ids[0]);
+        let (_, graph) = call(app, Method::GET, &uri, None).await;
+        assert_eq!(graph["edges"].as_array().unwrap().len(), 1);
+
+        let uri = format!("/vertexes/{}/edges?direction=in", ids[1]);
+        let (_, edges) = call(app, Method::GET, &uri, None).await;
+        assert_eq!(edges, json!([ab]));
+
+        // an edge which does not exist
+        let uri = format!("/edges/{}", ObjectId::new().to_hex());
+        let (status, _) = call(app, Method::GET, &uri, None).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        let update = json!({"source": ids[0], "target": ids[2]});
+        let (status, _) = call(app, Method::PUT, &uri, Some(update)).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        let uri = format!("/edges/{}", ab["id"].as_str().unwrap());
+        let update = json!({"source": ids[0], "target": ids[2], "weight": 2.0});
+        let (_, edge) = call(app, Method::PUT, &uri, Some(update)).await;
+        assert_eq!(
+            (edge["target"].as_str(), edge["weight"].as_f64()),
+            (Some(&*ids[2]), Some(2.0))
+        );
+
+        // endpoints must exist
+        let missing = json!({"source": ids[0], "target": ObjectId::new().to_hex()});
+        let (status, _) = call(app, Method::POST, "/edges", Some(missing)).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_import_export() {
+        for (store, _graph) in stores("import_export").await {
+            import_export(&router(store)).await;
+        }
+    }
+
+    async fn import_export(app: &Router) {
+        let a = ObjectId::new().to_hex();
+        let graph = json!({
+            "vertexes": [
+                {"id": a, "name": "a", "properties": {"x": 1}},
+                {"name": "b"},
+            ],
+            "edges": [{"source": a, "target": a}],
+        });
+        let (status, inserted) = call(app, Method::POST, "/graph", Some(graph)).await;
+        assert_eq!(status, StatusCode::CREATED);
+        assert!(inserted["vertexes"][1]["id"].is_string());
+
+        let (_, exported) = call(app, Method::GET, "/graph", None).await;
+        assert_eq!(exported, inserted);
+        assert_eq!(exported["vertexes"][0]["properties"], json!({"x": 1}));
+
+        let (_, stats) = call(app, Method::GET, "/stats", None).await;
+        assert_eq!(
+            (stats["vertex_count"].as_u64(), stats["edge_count"].as_u64()),
+            (Some(2), Some(1))
+        );
+
+        let bad = json!({"vertexes": [{"name": "c", "properties": [1]}]});
+        let (status, _) = call(app, Method::POST, "/graph", Some(bad)).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        // a taken id, or one given twice, fails the whole batch
+        let taken = json!({"vertexes": [{"name": "d"}, {"id": a, "name": "e"}]});
+        let (status, _) = call(app, Method::POST, "/graph", Some(taken)).await;
+        assert_eq!(status, StatusCode::CONFLICT);
+        let f = ObjectId::new().to_hex();
+        let twice = json!({"vertexes": [{"id": f, "name": "f"}, {"id": f, "name": "g"}]});
+        let (status, _) = call(app, Method::POST, "/graph", Some(twice)).await;
+        assert_eq!(status, StatusCode::CONFLICT);
+        let (_, stats) = call(app, Method::GET, "/stats", None).await;
+        assert_eq!(stats["vertex_count"].as_u64(), Some(2));
+    }
+
+    #[test]
+    fn test_status() {
+        let retried = Pyo3MongoError::Retried {
+            attempts: 3,
+            source: Box::new(Pyo3MongoError::Conflict("duplicate".to_string())),
+        };
+        assert_eq!(status(&retried), StatusCode::CONFLICT);
+    }
+
+    // every documented operation is routed: errors of the handlers have a body,
+    // unlike the ones of the router
+    #[tokio::test]
+    async fn test_openapi() {
+        let app = app();
+        let (_, doc) = call(&app, Method::GET, "/openapi.json", None).await;
+        assert_eq!(doc, openapi::document());
+
+        let id = ObjectId::new().to_hex();
+        for (path, item) in doc["paths"].as_object().unwrap() {
+            let uri = path.replace("{id}", &id);
+            let methods = item.as_object().unwrap().keys();
+            for method in methods.filter(|k| *k != "parameters") {
+                let method = Method::from_str(&method.to_uppercase()).unwrap();
+                let body = (method == Method::POST || method == Method::PUT).then(|| json!({}));
+                let (status, value) = call(&app, method.clone(), &uri, body).await;
+                let routed = status != StatusCode::METHOD_NOT_ALLOWED
+                    && (status != StatusCode::NOT_FOUND || value.is_object());
+                assert!(routed, "{} {} is not routed: {}", method, path, status);
+            }
+        }
+    }
+}
@@ -0,0 +1,35 @@
+use clap::Parser;
+use p3m::telemetry;
+use p3m::{GraphService, MongoConfig, Pyo3MongoResult};
+
+#[derive(Parser, Debug)]
+#[clap(about, version, author)]
+struct Args {
+    /// JSON config file, `MONGO_*` environment variables are read if absent
+    #[clap(long)]
+    config: Option<String>,
+
+    #[clap(short, long)]
+    uri: Option<String>,
+
+    #[clap(short, long)]
+    database: Option<String>,
+
+    #[clap(short, long, default_value = "dev")]
+    category: String,
+}
+
+#[tokio::main]
+async fn main() -> Pyo3MongoResult<()> {
+    let args = Args::parse();
+    telemetry::init_tracing("p3m=warn");
+
+    let config = MongoConfig::load(args.config.as_deref())?.overrides(args.uri, args.database);
+    let gs = GraphService::with_config(&config, &args.category).await?;
+
+    let stats = gs.stats().await?;
+
+    println!("{}", serde_json::to_string_pretty(&stats)?);
+
+    Ok(())
+}
@@ -0,0 +1,54 @@
+use clap::Parser;
+use p3m::telemetry;
+use p3m::{DiffKey, GraphService, MongoConfig, Pyo3MongoResult};
+
+#[derive(Parser, Debug)]
+#[clap(about, version, author)]
+struct Args {
+    /// JSON config file, `MONGO_*` environment variables are read if absent
+    #[clap(long)]
+    config: Option<String>,
+
+    #[clap(short, long)]
+    uri: Option<String>,
+
+    #[clap(short, long)]
+    database: Option<String>,
+
+    /// category whose changes are promoted
+    #[clap(long, default_value = "dev")]
+    from: String,
+
+    /// category to be synced
+    #[clap(long, default_value = "prod")]
+    to: String,
+
+    /// pair vertexes by name & edges by endpoints and label, rather than by id
+    #[clap(long)]
+    natural: bool,
+
+    /// sync the target, otherwise only report the diff (dry run)
+    #[clap(long)]
+    apply: bool,
+}
+
+#[tokio::main]
+async fn main() -> Pyo3MongoResult<()> {
+    let args = Args::parse();
+    telemetry::init_tracing("p3m=warn");
+
+    let config = MongoConfig::load(args.config.as_deref())?.overrides(args.uri, args.database);
+    let gs = GraphService::with_config(&config, &args.to).await?;
+
+    let key = if args.natural {
+        DiffKey::Natural
+    } else {
+        DiffKey::Id
+    };
+    let diff = gs.diff(&args.from, key).await?;
+    gs.apply_diff(&diff, !args.apply).await?;
+
+    println!("{}", serde_json::to_string_pretty(&diff)?);
+
+    Ok(())
+}
@@ -1,14 +1,19 @@
 use clap::Parser;
-use p3m::{GraphService, Pyo3MongoResult};
+use p3m::telemetry;
+use p3m::{GraphService, MongoConfig, Pyo3MongoResult};
 
 #[derive(Parser, Debug)]
 #[clap(about, version, author)]
 struct Args {
+    /// JSON config file, `MONGO_*` environment variables are read if absent
+    #[clap(long)]
+    config: Option<String>,
+
     #[clap(short, long)]
-    uri: String,
+    uri: Option<String>,
 
-    #[clap(short, long, default_value = "graph")]
-    database: String,
+    #[clap(short, long)]
+    database: Option<String>,
 
     #[clap(short, long, default_value = "dev")]
     category: String,
@@ -17,8 +22,10 @@ struct Args {
 #[tokio::main]
 async fn main() -> Pyo3MongoResult<()> {
     let args = Args::parse();
+    telemetry::init_tracing("p3m=warn");
 
-    let gs = GraphService::new(&args.uri, &args.database, &args.category).await?;
+    let config = MongoConfig::load(args.config.as_deref())?.overrides(args.uri, args.database);
+    let gs = GraphService::with_config(&config, &args.category).await?;
 
     gs.truncate_all().await
 }
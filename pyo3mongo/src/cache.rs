@@ -0,0 +1,328 @@
+//! Cache
+//!
+//! Read-through cache of `GraphService`: vertex lookups and traversals are kept
+//! in LRU caches, optionally expiring after a TTL. Any write made through the
+//! same service clears them, writes made by others are only seen once entries
+//! expire (or are evicted).
+
+use std::hash::Hash;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use lru::LruCache;
+use mongodb::bson::{oid::ObjectId, DateTime};
+use pyo3::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::model::{Edge, Vertex};
+
+/// capacity & lifetime of cached entries
+#[derive(Debug, Clone, Copy)]
+pub struct CacheOptions {
+    /// entries per cache, the least recently used one is evicted beyond
+    pub capacity: usize,
+    /// entries expire after `ttl`, never if absent
+    pub ttl: Option<Duration>,
+}
+
+impl Default for CacheOptions {
+    fn default() -> Self {
+        CacheOptions {
+            capacity: 1024,
+            ttl: None,
+        }
+    }
+}
+
+/// hit/miss metrics of a cache
+#[pyclass(module = "p3m")]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct CacheMetrics {
+    #[pyo3(get)]
+    pub hits: u64,
+    #[pyo3(get)]
+    pub misses: u64,
+    /// entries dropped to make room, expired ones are not counted
+    #[pyo3(get)]
+    pub evictions: u64,
+    /// entries currently kept
+    #[pyo3(get)]
+    pub size: u64,
+}
+
+/// metrics of the caches of a `GraphService`
+#[pyclass(module = "p3m")]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    /// `get_vertex`
+    #[pyo3(get)]
+    pub vertexes: CacheMetrics,
+    /// `get_graph_from_vertex_by_label`
+    #[pyo3(get)]
+    pub traversals: CacheMetrics,
+    /// times the caches were cleared by a write
+    #[pyo3(get)]
+    pub invalidations: u64,
+}
+
+/// an LRU cache whose entries may expire
+struct Lru<K: Hash + Eq, V> {
+    entries: Mutex<LruCache<K, (Instant, V)>>,
+    ttl: Option<Duration>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    evictions: AtomicU64,
+}
+
+impl<K: Hash + Eq, V: Clone> Lru<K, V> {
+    fn new(options: &CacheOptions) -> Self {
+        let capacity = std::num::NonZeroUsize::new(options.capacity.max(1)).unwrap();
+        Lru {
+            entries: Mutex::new(LruCache::new(capacity)),
+            ttl: options.ttl,
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            evictions: AtomicU64::new(0),
+        }
+    }
+
+    fn get(&self, key: &K) -> Option<V> {
+        let mut entries = self.entries.lock().unwrap();
+
+        let expired = match entries.get(key) {
+            Some((at, _)) => self.ttl.is_some_and(|ttl| at.elapsed() >= ttl),
+            None => {
+                self.misses.fetch_add(1, Ordering::Relaxed);
+                return None;
+            }
+        };
+        if expired {
+            entries.pop(key);
+            self.misses.fetch_add(1, Ordering::Relaxed);
+            return None;
+        }
+
+        self.hits.fetch_add(1, Ordering::Relaxed);
+        entries.get(key).map(|(_, v)| v.clone())
+    }
+
+    fn put(&self, key: K, value: V) {
+        let mut entries = self.entries.lock().unwrap();
+
+        // `push` returns the replaced entry as well, which is not an eviction
+        let full = entries.len() == entries.cap().get() && !entries.contains(&key);
+        entries.push(key, (Instant::now(), value));
+        if full {
+            self.evictions.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    fn clear(&self) {
+        self.entries.lock().unwrap().clear();
+    }
+
+    fn metrics(&self) -> CacheMetrics {
+        CacheMetrics {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            evictions: self.evictions.load(Ordering::Relaxed),
+            size: self.entries.lock().unwrap().len() as u64,
+        }
+    }
+}
+
+/// a traversal is identified by its arguments
+#[derive(Hash, PartialEq, Eq, Clone, Debug)]
+pub struct TraversalKey {
+    pub vertex_id: ObjectId,
+    pub label: Option<String>,
+    pub depth: Option<i32>,
+    pub at: Option<DateTime>,
+}
+
+/// caches of a `GraphService`, see the module doc
+pub struct GraphCache {
+    vertexes: Lru<ObjectId, Vertex>,
+    traversals: Lru<TraversalKey, (Vec<Edge>, Vec<Vertex>)>,
+    /// bumped whenever a write begins or ends
+    generation: AtomicU64,
+    /// writes in progress
+    writing: AtomicU64,
+    invalidations: AtomicU64,
+}
+
+impl GraphCache {
+    pub fn new(options: CacheOptions) -> Self {
+        GraphCache {
+            vertexes: Lru::new(&options),
+            traversals: Lru::new(&options),
+            generation: AtomicU64::new(0),
+            writing: AtomicU64::new(0),
+            invalidations: AtomicU64::new(0),
+        }
+    }
+
+    pub fn get_vertex(&self, id: &ObjectId) -> Option<Vertex> {
+        self.vertexes.get(id)
+    }
+
+    pub fn get_traversal(&self, key: &TraversalKey) -> Option<(Vec<Edge>, Vec<Vertex>)> {
+        self.traversals.get(key)
+    }
+
+    /// a token to be given back to `put_*`, taken before reading the database
+    pub fn read(&self) -> Option<u64> {
+        // a value read while writing may be outdated as soon as it is read
+        if self.writing.load(Ordering::SeqCst) > 0 {
+            return None;
+        }
+        Some(self.generation.load(Ordering::SeqCst))
+    }
+
+    // only if no write has begun since `token` was taken
+    fn is_fresh(&self, token: Option<u64>) -> bool {
+        token == Some(self.generation.load(Ordering::SeqCst))
+    }
+
+    pub fn put_vertex(&self, token: Option<u64>, vertex: Vertex) {
+        if let (true, Some(id)) = (self.is_fresh(token), vertex.id) {
+            self.vertexes.put(id, vertex);
+        }
+    }
+
+    pub fn put_traversal(
+        &self,
+        token: Option<u64>,
+        key: TraversalKey,
+        graph: (Vec<Edge>, Vec<Vertex>),
+    ) {
+        if self.is_fresh(token) {
+            self.traversals.put(key, graph);
+        }
+    }
+
+    /// the caches are cleared now and once the returned guard is dropped, i.e.
+    /// before and after the write, values read in between are not kept
+    pub fn write(&self) -> WriteGuard<'_> {
+        self.writing.fetch_add(1, Ordering::SeqCst);
+        self.invalidate();
+        WriteGuard { cache: self }
+    }
+
+    fn invalidate(&self) {
+        self.generation.fetch_add(1, Ordering::SeqCst);
+        self.vertexes.clear();
+        self.traversals.clear();
+        self.invalidations.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            vertexes: self.vertexes.metrics(),
+            traversals: self.traversals.metrics(),
+            invalidations: self.invalidations.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// a write in progress, see `GraphCache::write`
+pub struct WriteGuard<'a> {
+    cache: &'a GraphCache,
+}
+
+impl Drop for WriteGuard<'_> {
+    fn drop(&mut self) {
+        self.cache.invalidate();
+        self.cache.writing.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+#[cfg(test)]
+mod test_cache {
+    use super::*;
+
+    fn vertex(name: &str) -> Vertex {
+        Vertex {
+            id: Some(ObjectId::new()),
+            name: name.to_owned(),
+            properties: None,
+        }
+    }
+
+    #[test]
+    fn test_lru() {
+        let cache = GraphCache::new(CacheOptions {
+            capacity: 2,
+            ttl: None,
+        });
+        let (v1, v2, v3) = (vertex("node-1"), vertex("node-2"), vertex("node-3"));
+        let id = |v: &Vertex| v.id.unwrap();
+
+        assert_eq!(cache.get_vertex(&id(&v1)), None);
+        for v in [&v1, &v2] {
+            cache.put_vertex(cache.read(), v.clone());
+        }
+        assert_eq!(cache.get_vertex(&id(&v1)), Some(v1.clone()));
+
+        // `v2` is the least recently used one
+        cache.put_vertex(cache.read(), v3.clone());
+        assert_eq!(cache.get_vertex(&id(&v2)), None);
+        assert_eq!(cache.get_vertex(&id(&v3)), Some(v3));
+
+        let metrics = cache.stats().vertexes;
+        assert_eq!(
+            metrics,
+            CacheMetrics {
+                hits: 2,
+                misses: 2,
+                evictions: 1,
+                size: 2,
+            }
+        );
+    }
+
+    #[test]
+    fn test_ttl() {
+        let cache = GraphCache::new(CacheOptions {
+            capacity: 8,
+            ttl: Some(Duration::from_millis(20)),
+        });
+        let key = TraversalKey {
+            vertex_id: ObjectId::new(),
+            label: Some("knows".to_owned()),
+            depth: None,
+            at: None,
+        };
+
+        cache.put_traversal(cache.read(), key.clone(), (vec![], vec![vertex("node-1")]));
+        assert!(cache.get_traversal(&key).is_some());
+
+        std::thread::sleep(Duration::from_millis(30));
+        assert!(cache.get_traversal(&key).is_none());
+        assert_eq!(cache.stats().traversals.size, 0);
+    }
+
+    #[test]
+    fn test_write() {
+        let cache = GraphCache::new(CacheOptions::default());
+        let v1 = vertex("node-1");
+
+        cache.put_vertex(cache.read(), v1.clone());
+        let before = cache.read();
+        {
+            let _write = cache.write();
+            assert_eq!(cache.stats().vertexes.size, 0);
+            // read while writing
+            assert_eq!(cache.read(), None);
+        }
+
+        // read before the write, put after
+        cache.put_vertex(before, v1.clone());
+        assert_eq!(cache.get_vertex(&v1.id.unwrap()), None);
+
+        cache.put_vertex(cache.read(), v1.clone());
+        assert_eq!(cache.get_vertex(&v1.id.unwrap()), Some(v1));
+        assert_eq!(cache.stats().invalidations, 2);
+    }
+}
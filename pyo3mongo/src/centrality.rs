@@ -0,0 +1,301 @@
+//! Centrality
+//!
+//! Pure graph algorithms computed in memory, over vertexes & edges which have
+//! already been fetched from MongoDB (see `GraphService::pagerank` and friends).
+//!
+//! Every algorithm treats the graph as directed: `source -> target`.
+
+use std::collections::{HashMap, VecDeque};
+
+use mongodb::bson::oid::ObjectId;
+
+use crate::Edge;
+
+/// parameters of PageRank
+#[derive(Debug, Clone, Copy)]
+pub struct PageRankOptions {
+    pub damping: f64,
+    pub max_iter: usize,
+    pub tolerance: f64,
+}
+
+impl Default for PageRankOptions {
+    fn default() -> Self {
+        PageRankOptions {
+            damping: 0.85,
+            max_iter: 100,
+            tolerance: 1e-6,
+        }
+    }
+}
+
+/// adjacency list representation, vertexes are indexed by their position
+pub(crate) struct Adjacency {
+    pub(crate) ids: Vec<ObjectId>,
+    // (target index, weight)
+    pub(crate) out: Vec<Vec<(usize, f64)>>,
+}
+
+impl Adjacency {
+    /// vertexes which only appear in edges are also taken into account
+    pub(crate) fn new(vertexes: &[ObjectId], edges: &[Edge]) -> Self {
+        let mut ids = Vec::new();
+        let mut index = HashMap::new();
+        let mut index_of = |id: ObjectId, ids: &mut Vec<ObjectId>| {
+            *index.entry(id).or_insert_with(|| {
+                ids.push(id);
+                ids.len() - 1
+            })
+        };
+
+        for v in vertexes {
+            index_of(*v, &mut ids);
+        }
+
+        let mut links = Vec::with_capacity(edges.len());
+        for e in edges {
+            let s = index_of(e.source, &mut ids);
+            let t = index_of(e.target, &mut ids);
+            links.push((s, t, e.weight.unwrap_or(1.0)));
+        }
+
+        let mut out = vec![Vec::new(); ids.len()];
+        for (s, t, w) in links {
+            out[s].push((t, w));
+        }
+
+        Adjacency { ids, out }
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    fn into_map(self, scores: Vec<f64>) -> HashMap<ObjectId, f64> {
+        self.ids.into_iter().zip(scores).collect()
+    }
+
+    /// hop distances from `s`, `None` if unreachable
+    fn bfs(&self, s: usize) -> Vec<Option<usize>> {
+        let mut dist = vec![None; self.len()];
+        dist[s] = Some(0);
+
+        let mut queue = VecDeque::from([s]);
+        while let Some(v) = queue.pop_front() {
+            let d = dist[v].unwrap();
+            for &(w, _) in &self.out[v] {
+                if dist[w].is_none() {
+                    dist[w] = Some(d + 1);
+                    queue.push_back(w);
+                }
+            }
+        }
+
+        dist
+    }
+}
+
+/// PageRank, `Edge.weight` is used as the transition weight (1.0 if absent).
+///
+/// Dangling vertexes (without any outgoing edge) spread their rank evenly.
+pub fn pagerank(
+    vertexes: &[ObjectId],
+    edges: &[Edge],
+    options: PageRankOptions,
+) -> HashMap<ObjectId, f64> {
+    let adj = Adjacency::new(vertexes, edges);
+    let n = adj.len();
+    if n == 0 {
+        return HashMap::new();
+    }
+
+    let nf = n as f64;
+    let out_weight = adj
+        .out
+        .iter()
+        .map(|o| o.iter().map(|(_, w)| w).sum::<f64>())
+        .collect::<Vec<_>>();
+
+    let mut rank = vec![1.0 / nf; n];
+    for _ in 0..options.max_iter {
+        let dangling = (0..n)
+            .filter(|&v| out_weight[v] <= 0.0)
+            .map(|v| rank[v])
+            .sum::<f64>();
+        let base = (1.0 - options.damping) / nf + options.damping * dangling / nf;
+
+        let mut next = vec![base; n];
+        for v in 0..n {
+            if out_weight[v] <= 0.0 {
+                continue;
+            }
+            for &(t, w) in &adj.out[v] {
+                next[t] += options.damping * rank[v] * w / out_weight[v];
+            }
+        }
+
+        let err = next
+            .iter()
+            .zip(rank.iter())
+            .map(|(a, b)| (a - b).abs())
+            .sum::<f64>();
+        rank = next;
+        if err < nf * options.tolerance {
+            break;
+        }
+    }
+
+    adj.into_map(rank)
+}
+
+/// betweenness centrality (Brandes' algorithm), hop count as distance.
+///
+/// If `normalized`, scores are divided by `(n - 1)(n - 2)`.
+pub fn betweenness(
+    vertexes: &[ObjectId],
+    edges: &[Edge],
+    normalized: bool,
+) -> HashMap<ObjectId, f64> {
+    let adj = Adjacency::new(vertexes, edges);
+    let n = adj.len();
+    let mut cb = vec![0.0; n];
+
+    for s in 0..n {
+        let mut stack = Vec::new();
+        let mut preds = vec![Vec::new(); n];
+        let mut sigma = vec![0.0; n];
+        let mut dist = vec![-1i64; n];
+        sigma[s] = 1.0;
+        dist[s] = 0;
+
+        let mut queue = VecDeque::from([s]);
+        while let Some(v) = queue.pop_front() {
+            stack.push(v);
+            for &(w, _) in &adj.out[v] {
+                if dist[w] < 0 {
+                    dist[w] = dist[v] + 1;
+                    queue.push_back(w);
+                }
+                if dist[w] == dist[v] + 1 {
+                    sigma[w] += sigma[v];
+                    preds[w].push(v);
+                }
+            }
+        }
+
+        let mut delta = vec![0.0; n];
+        while let Some(w) = stack.pop() {
+            for &v in &preds[w] {
+                delta[v] += sigma[v] / sigma[w] * (1.0 + delta[w]);
+            }
+            if w != s {
+                cb[w] += delta[w];
+            }
+        }
+    }
+
+    if normalized && n > 2 {
+        let scale = 1.0 / ((n - 1) * (n - 2)) as f64;
+        cb.iter_mut().for_each(|c| *c *= scale);
+    }
+
+    adj.into_map(cb)
+}
+
+/// closeness centrality, hop count as distance, following outgoing edges.
+///
+/// Wasserman & Faust's formula is used, so that vertexes reaching only a part
+/// of the graph are not overrated: `(r / sum) * (r / (n - 1))`, where `r` is the
+/// number of reachable vertexes and `sum` the total distance to them.
+pub fn closeness(vertexes: &[ObjectId], edges: &[Edge]) -> HashMap<ObjectId, f64> {
+    let adj = Adjacency::new(vertexes, edges);
+    let n = adj.len();
+
+    let scores = (0..n)
+        .map(|s| {
+            let (r, sum) = adj
+                .bfs(s)
+                .into_iter()
+                .flatten()
+                .filter(|&d| d > 0)
+                .fold((0usize, 0usize), |(r, sum), d| (r + 1, sum + d));
+
+            if sum == 0 {
+                0.0
+            } else {
+                (r as f64 / sum as f64) * (r as f64 / (n - 1) as f64)
+            }
+        })
+        .collect();
+
+    adj.into_map(scores)
+}
+
+#[cfg(test)]
+mod test_centrality {
+    use super::*;
+
+    fn edge(source: ObjectId, target: ObjectId, weight: Option<f64>) -> Edge {
+        Edge {
+            id: None,
+            source,
+            target,
+            weight,
+            label: None,
+            valid_from: None,
+            valid_to: None,
+        }
+    }
+
+    fn ids(n: usize) -> Vec<ObjectId> {
+        (0..n).map(|_| ObjectId::new()).collect()
+    }
+
+    #[test]
+    fn test_pagerank() {
+        // v0 -> v1 -> v2 -> v0, a circuit: every vertex has the same rank
+        let v = ids(3);
+        let edges = vec![
+            edge(v[0], v[1], None),
+            edge(v[1], v[2], None),
+            edge(v[2], v[0], None),
+        ];
+        let pr = pagerank(&v, &edges, PageRankOptions::default());
+        for id in &v {
+            assert!((pr[id] - 1.0 / 3.0).abs() < 1e-6);
+        }
+
+        // weights matter: v0 -> v1 (9.0), v0 -> v2 (1.0)
+        let edges = vec![edge(v[0], v[1], Some(9.0)), edge(v[0], v[2], Some(1.0))];
+        let pr = pagerank(&v, &edges, PageRankOptions::default());
+        assert!(pr[&v[1]] > pr[&v[2]]);
+        assert!((pr.values().sum::<f64>() - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_betweenness() {
+        // v0 -> v1 -> v2, v1 is the only bridge
+        let v = ids(3);
+        let edges = vec![edge(v[0], v[1], None), edge(v[1], v[2], None)];
+        let bc = betweenness(&v, &edges, false);
+        assert_eq!(bc[&v[0]], 0.0);
+        assert_eq!(bc[&v[1]], 1.0);
+        assert_eq!(bc[&v[2]], 0.0);
+
+        let bc = betweenness(&v, &edges, true);
+        assert_eq!(bc[&v[1]], 0.5);
+    }
+
+    #[test]
+    fn test_closeness() {
+        // v0 -> v1 -> v2
+        let v = ids(3);
+        let edges = vec![edge(v[0], v[1], None), edge(v[1], v[2], None)];
+        let cc = closeness(&v, &edges);
+        // reaches 2 vertexes with total distance 3
+        assert!((cc[&v[0]] - 2.0 / 3.0).abs() < 1e-9);
+        // reaches 1 vertex with total distance 1
+        assert!((cc[&v[1]] - 0.5).abs() < 1e-9);
+        assert_eq!(cc[&v[2]], 0.0);
+    }
+}
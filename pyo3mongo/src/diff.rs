@@ -0,0 +1,344 @@
+//! Diff
+//!
+//! Differences between two graphs, computed in memory over vertexes & edges
+//! which have already been fetched from MongoDB (see `GraphService::diff`).
+//! Vertexes & edges are paired by id, or by a natural key, then reported as
+//! added, removed or changed.
+//!
+//! A diff turns a base graph into another one, and is expressed in ids of the
+//! base: vertexes to be added are given their ids beforehand, and edges refer
+//! to vertexes by their ids in the base. Applying it (see
+//! `GraphService::apply_diff`) only needs the base.
+
+use std::collections::{HashMap, VecDeque};
+use std::hash::Hash;
+use std::str::FromStr;
+
+use mongodb::bson::oid::ObjectId;
+use pyo3::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::model::{Edge, Vertex};
+use crate::{Pyo3MongoError, Pyo3MongoResult};
+
+/// how vertexes & edges of two graphs are paired
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiffKey {
+    /// by `_id`, for a graph which has been copied from the other one
+    Id,
+    /// vertexes by name, edges by endpoints & label. Vertexes (or edges)
+    /// sharing a key are paired in order.
+    Natural,
+}
+
+impl FromStr for DiffKey {
+    type Err = Pyo3MongoError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "id" => Ok(DiffKey::Id),
+            "natural" => Ok(DiffKey::Natural),
+            _ => Err(Pyo3MongoError::Common("unknown diff key")),
+        }
+    }
+}
+
+/// a vertex found in both graphs, which differs
+#[pyclass(module = "p3m")]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct VertexChange {
+    /// as in the base
+    #[pyo3(get)]
+    pub before: Vertex,
+    /// as in the other graph, with the id of the base
+    #[pyo3(get)]
+    pub after: Vertex,
+}
+
+/// an edge found in both graphs, which differs
+#[pyclass(module = "p3m")]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct EdgeChange {
+    /// as in the base
+    #[pyo3(get)]
+    pub before: Edge,
+    /// as in the other graph, with ids of the base
+    #[pyo3(get)]
+    pub after: Edge,
+}
+
+/// what turns a base graph into another one, see the module doc
+#[pyclass(module = "p3m")]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct GraphDiff {
+    #[pyo3(get)]
+    pub added_vertexes: Vec<Vertex>,
+    #[pyo3(get)]
+    pub removed_vertexes: Vec<Vertex>,
+    #[pyo3(get)]
+    pub changed_vertexes: Vec<VertexChange>,
+    #[pyo3(get)]
+    pub added_edges: Vec<Edge>,
+    #[pyo3(get)]
+    pub removed_edges: Vec<Edge>,
+    #[pyo3(get)]
+    pub changed_edges: Vec<EdgeChange>,
+}
+
+impl GraphDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added_vertexes.is_empty()
+            && self.removed_vertexes.is_empty()
+            && self.changed_vertexes.is_empty()
+            && self.added_edges.is_empty()
+            && self.removed_edges.is_empty()
+            && self.changed_edges.is_empty()
+    }
+}
+
+/// items paired by key, by their positions
+struct Pairing {
+    /// `(base, other)`
+    matched: Vec<(usize, usize)>,
+    base_only: Vec<usize>,
+    other_only: Vec<usize>,
+}
+
+// items sharing a key are paired in order
+fn pair<T, K: Hash + Eq>(base: &[T], other: &[T], key: impl Fn(&T) -> K) -> Pairing {
+    let mut unmatched = HashMap::<K, VecDeque<usize>>::new();
+    for (i, b) in base.iter().enumerate() {
+        unmatched.entry(key(b)).or_default().push_back(i);
+    }
+
+    let mut matched = Vec::new();
+    let mut other_only = Vec::new();
+    let mut paired = vec![false; base.len()];
+    for (j, o) in other.iter().enumerate() {
+        match unmatched.get_mut(&key(o)).and_then(VecDeque::pop_front) {
+            Some(i) => {
+                paired[i] = true;
+                matched.push((i, j));
+            }
+            None => other_only.push(j),
+        }
+    }
+    let base_only = (0..base.len()).filter(|i| !paired[*i]).collect();
+
+    Pairing {
+        matched,
+        base_only,
+        other_only,
+    }
+}
+
+/// what turns `base` into `other`, both given as `(vertexes, edges)`. With a
+/// natural key, edges of `other` must not be dangling.
+pub fn diff(
+    base: (&[Vertex], &[Edge]),
+    other: (&[Vertex], &[Edge]),
+    key: DiffKey,
+) -> Pyo3MongoResult<GraphDiff> {
+    let (base_vertexes, base_edges) = base;
+    let (other_vertexes, other_edges) = other;
+    let mut res = GraphDiff::default();
+
+    // a vertex added (or an edge) keeps its id if paired by id, so that both
+    // graphs stay aligned; it is given a new one otherwise
+    let new_id = |id: Option<ObjectId>| match key {
+        DiffKey::Id => id,
+        DiffKey::Natural => Some(ObjectId::new()),
+    };
+
+    let pairing = match key {
+        DiffKey::Id => pair(base_vertexes, other_vertexes, |v| v.id),
+        DiffKey::Natural => pair(base_vertexes, other_vertexes, |v| v.name.clone()),
+    };
+    // ids of `other` to ids of `base`
+    let mut ids = HashMap::new();
+    for (i, j) in pairing.matched {
+        let (before, after) = (&base_vertexes[i], &other_vertexes[j]);
+        let after = Vertex {
+            id: before.id,
+            ..after.clone()
+        };
+        ids.insert(other_vertexes[j].id, before.id);
+        if &after != before {
+            res.changed_vertexes.push(VertexChange {
+                before: before.clone(),
+                after,
+            });
+        }
+    }
+    for j in pairing.other_only {
+        let vertex = Vertex {
+            id: new_id(other_vertexes[j].id),
+            ..other_vertexes[j].clone()
+        };
+        ids.insert(other_vertexes[j].id, vertex.id);
+        res.added_vertexes.push(vertex);
+    }
+    for i in pairing.base_only {
+        res.removed_vertexes.push(base_vertexes[i].clone());
+    }
+
+    // endpoints of `other` in ids of `base`; a dangling endpoint is kept as it
+    // is if paired by id, there is no way to tell which vertex it is otherwise
+    let translate = |e: &Edge| -> Pyo3MongoResult<Edge> {
+        let endpoint = |id: ObjectId| match (ids.get(&Some(id)), key) {
+            (Some(Some(id)), _) => Ok(*id),
+            (_, DiffKey::Id) => Ok(id),
+            (_, DiffKey::Natural) => Err(Pyo3MongoError::Conflict(format!(
+                "edge {:?} refers to a missing vertex {}",
+                e.id, id
+            ))),
+        };
+        Ok(Edge {
+            source: endpoint(e.source)?,
+            target: endpoint(e.target)?,
+            ..e.clone()
+        })
+    };
+    let other_edges = other_edges
+        .iter()
+        .map(translate)
+        .collect::<Pyo3MongoResult<Vec<_>>>()?;
+
+    let pairing = match key {
+        DiffKey::Id => pair(base_edges, &other_edges, |e| e.id),
+        DiffKey::Natural => pair(base_edges, &other_edges, |e| {
+            (e.source, e.target, e.label.clone())
+        }),
+    };
+    for (i, j) in pairing.matched {
+        let before = &base_edges[i];
+        let after = Edge {
+            id: before.id,
+            ..other_edges[j].clone()
+        };
+        if &after != before {
+            res.changed_edges.push(EdgeChange {
+                before: before.clone(),
+                after,
+            });
+        }
+    }
+    for j in pairing.other_only {
+        res.added_edges.push(Edge {
+            id: new_id(other_edges[j].id),
+            ..other_edges[j].clone()
+        });
+    }
+    for i in pairing.base_only {
+        res.removed_edges.push(base_edges[i].clone());
+    }
+
+    Ok(res)
+}
+
+#[cfg(test)]
+mod test_diff {
+    use super::*;
+
+    fn vertex(name: &str) -> Vertex {
+        Vertex {
+            id: Some(ObjectId::new()),
+            name: name.to_owned(),
+            properties: None,
+        }
+    }
+
+    fn edge(source: &Vertex, target: &Vertex, label: &str) -> Edge {
+        Edge {
+            id: Some(ObjectId::new()),
+            source: source.id.unwrap(),
+            target: target.id.unwrap(),
+            weight: None,
+            label: Some(label.to_owned()),
+            valid_from: None,
+            valid_to: None,
+        }
+    }
+
+    fn diff_of(
+        base: &(Vec<Vertex>, Vec<Edge>),
+        other: &(Vec<Vertex>, Vec<Edge>),
+        key: DiffKey,
+    ) -> GraphDiff {
+        diff((&base.0, &base.1), (&other.0, &other.1), key).unwrap()
+    }
+
+    #[test]
+    fn test_diff_by_id() {
+        let (v1, v2, v3) = (vertex("node-1"), vertex("node-2"), vertex("node-3"));
+        let (e1, e2) = (edge(&v1, &v2, "knows"), edge(&v2, &v3, "knows"));
+        let base = (
+            vec![v1.clone(), v2.clone(), v3.clone()],
+            vec![e1.clone(), e2.clone()],
+        );
+
+        // node-2 renamed, node-3 & its edge removed, node-4 & an edge added
+        let v2b = Vertex {
+            name: "node-2b".to_owned(),
+            ..v2.clone()
+        };
+        let v4 = vertex("node-4");
+        let e3 = edge(&v1, &v4, "likes");
+        let other = (vec![v1, v2b.clone(), v4.clone()], vec![e1, e3.clone()]);
+
+        let diff = diff_of(&base, &other, DiffKey::Id);
+        assert_eq!(diff.added_vertexes, vec![v4]);
+        assert_eq!(diff.removed_vertexes, vec![v3]);
+        assert_eq!(
+            diff.changed_vertexes,
+            vec![VertexChange {
+                before: v2,
+                after: v2b
+            }]
+        );
+        assert_eq!(diff.added_edges, vec![e3]);
+        assert_eq!(diff.removed_edges, vec![e2]);
+        assert!(diff.changed_edges.is_empty());
+
+        let same = diff_of(&base, &base, DiffKey::Id);
+        assert!(same.is_empty());
+    }
+
+    #[test]
+    fn test_diff_natural() {
+        let (v1, v2) = (vertex("node-1"), vertex("node-2"));
+        let base = (vec![v1.clone(), v2.clone()], vec![edge(&v1, &v2, "knows")]);
+
+        // the same graph, with other ids, but a weight & a new vertex
+        let (w1, w2, w3) = (vertex("node-1"), vertex("node-2"), vertex("node-3"));
+        let weighted = Edge {
+            weight: Some(2.0),
+            ..edge(&w1, &w2, "knows")
+        };
+        let added = edge(&w2, &w3, "knows");
+        let other = (vec![w1, w2, w3.clone()], vec![weighted, added]);
+
+        let diff = diff_of(&base, &other, DiffKey::Natural);
+        assert!(diff.removed_vertexes.is_empty() && diff.removed_edges.is_empty());
+        assert!(diff.changed_vertexes.is_empty());
+
+        // in ids of the base, the added vertex given a new one
+        assert_eq!(diff.added_vertexes.len(), 1);
+        let added = &diff.added_vertexes[0];
+        assert_eq!(added.name, "node-3");
+        assert_ne!(added.id, w3.id);
+        assert_eq!(diff.added_edges.len(), 1);
+        assert_eq!(diff.added_edges[0].source, v2.id.unwrap());
+        assert_eq!(diff.added_edges[0].target, added.id.unwrap());
+
+        assert_eq!(diff.changed_edges.len(), 1);
+        let change = &diff.changed_edges[0];
+        assert_eq!(change.after.id, change.before.id);
+        assert_eq!(change.after.weight, Some(2.0));
+
+        // a dangling edge cannot be paired by name
+        let other = (vec![], vec![edge(&v1, &v2, "knows")]);
+        let res = super::diff((&base.0, &base.1), (&other.0, &other.1), DiffKey::Natural);
+        assert!(matches!(res, Err(Pyo3MongoError::Conflict(_))));
+    }
+}
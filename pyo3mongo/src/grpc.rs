@@ -0,0 +1,823 @@
+//! gRPC
+//!
+//! The contract is `proto/graph.proto`: `proto` holds the generated messages,
+//! along with the client (`proto::graph_client::GraphClient`) and the server
+//! (`proto::graph_server::GraphServer`). `GraphRpc` serves any `GraphStore`.
+//!
+//! Ids are hex strings, vertex properties are relaxed extended JSON (as the
+//! HTTP server does) and datetimes are `google.protobuf.Timestamp`.
+
+use std::collections::HashSet;
+use std::pin::Pin;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use futures::Stream;
+use mongodb::bson::{oid::ObjectId, Bson, DateTime};
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::{Code, Request, Response, Status};
+
+use crate::model::{
+    check_depth, Edge, EdgeDto, FindEdgeByVertexDto, GraphStats, IndexStatus, Vertex, VertexDto,
+};
+use crate::retry;
+use crate::store::GraphStore;
+use crate::{Pyo3MongoError, Pyo3MongoResult};
+
+/// messages & services generated from `proto/graph.proto`
+pub mod proto {
+    tonic::include_proto!("p3m");
+}
+
+use proto::find_edge_by_vertex_dto::Orientation;
+use proto::graph_server::{Graph, GraphServer};
+use proto::traversal_item::Item;
+
+impl From<Pyo3MongoError> for Status {
+    fn from(e: Pyo3MongoError) -> Self {
+        Status::new(code(&e), e.to_string())
+    }
+}
+
+// a retried error is mapped as its source, a transient one as unavailable
+fn code(e: &Pyo3MongoError) -> Code {
+    match e {
+        Pyo3MongoError::VertexNotFound
+        | Pyo3MongoError::EdgeNotFound
+        | Pyo3MongoError::HyperEdgeNotFound => Code::NotFound,
+        Pyo3MongoError::Common(_)
+        | Pyo3MongoError::Query(_)
+        | Pyo3MongoError::Oid(_)
+        | Pyo3MongoError::De(_) => Code::InvalidArgument,
+        Pyo3MongoError::Conflict(_) | Pyo3MongoError::Write(_) => Code::AlreadyExists,
+        Pyo3MongoError::Denied(_) => Code::PermissionDenied,
+        Pyo3MongoError::Retried { source, .. } => code(source),
+        e if retry::is_transient(e) => Code::Unavailable,
+        _ => Code::Internal,
+    }
+}
+
+fn oid(hex: &str) -> Pyo3MongoResult<ObjectId> {
+    Ok(ObjectId::from_str(hex)?)
+}
+
+// an empty id is a missing one
+fn optional_oid(hex: &str) -> Pyo3MongoResult<Option<ObjectId>> {
+    (!hex.is_empty()).then(|| oid(hex)).transpose()
+}
+
+fn oids(hexes: &[String]) -> Pyo3MongoResult<Vec<ObjectId>> {
+    hexes.iter().map(|h| oid(h)).collect()
+}
+
+fn hex(id: Option<ObjectId>) -> String {
+    id.map(|id| id.to_hex()).unwrap_or_default()
+}
+
+fn timestamp(dt: DateTime) -> prost_types::Timestamp {
+    let millis = dt.timestamp_millis();
+    prost_types::Timestamp {
+        seconds: millis.div_euclid(1000),
+        nanos: (millis.rem_euclid(1000) * 1_000_000) as i32,
+    }
+}
+
+// sub-millisecond precision is lost, as BSON datetimes are milliseconds
+fn datetime(ts: prost_types::Timestamp) -> Pyo3MongoResult<DateTime> {
+    ts.seconds
+        .checked_mul(1000)
+        .and_then(|ms| ms.checked_add(i64::from(ts.nanos) / 1_000_000))
+        .map(DateTime::from_millis)
+        .ok_or(Pyo3MongoError::Common("timestamp out of range"))
+}
+
+impl From<Vertex> for proto::Vertex {
+    fn from(v: Vertex) -> Self {
+        proto::Vertex {
+            id: hex(v.id),
+            name: v.name,
+            properties: v
+                .properties
+                .map(|p| Bson::Document(p).into_relaxed_extjson().to_string())
+                .unwrap_or_default(),
+        }
+    }
+}
+
+impl TryFrom<proto::Vertex> for Vertex {
+    type Error = Pyo3MongoError;
+
+    fn try_from(v: proto::Vertex) -> Result<Self, Self::Error> {
+        let properties = if v.properties.is_empty() {
+            None
+        } else {
+            let json = serde_json::from_str::<serde_json::Value>(&v.properties)?;
+            match Bson::try_from(json) {
+                Ok(Bson::Document(d)) => Some(d),
+                _ => return Err(Pyo3MongoError::Common("properties must be an object")),
+            }
+        };
+        Ok(Vertex {
+            id: optional_oid(&v.id)?,
+            name: v.name,
+            properties,
+        })
+    }
+}
+
+impl From<Edge> for proto::Edge {
+    fn from(e: Edge) -> Self {
+        proto::Edge {
+            id: hex(e.id),
+            source: e.source.to_hex(),
+            target: e.target.to_hex(),
+            weight: e.weight,
+            label: e.label,
+            valid_from: e.valid_from.map(timestamp),
+            valid_to: e.valid_to.map(timestamp),
+        }
+    }
+}
+
+impl proto::Edge {
+    fn dto(&self) -> Pyo3MongoResult<EdgeDto<'_>> {
+        let dto = EdgeDto::new(
+            oid(&self.source)?,
+            oid(&self.target)?,
+            self.weight,
+            self.label.as_deref(),
+        );
+        Ok(dto.valid_between(
+            self.valid_from.map(datetime).transpose()?,
+            self.valid_to.map(datetime).transpose()?,
+        ))
+    }
+}
+
+impl TryFrom<proto::Edge> for Edge {
+    type Error = Pyo3MongoError;
+
+    fn try_from(e: proto::Edge) -> Result<Self, Self::Error> {
+        Ok(Edge {
+            id: optional_oid(&e.id)?,
+            ..Edge::from(e.dto()?)
+        })
+    }
+}
+
+impl proto::GraphOutput {
+    fn new(vertexes: Vec<Vertex>, edges: Vec<Edge>) -> Self {
+        proto::GraphOutput {
+            vertexes: vertexes.into_iter().map(proto::Vertex::from).collect(),
+            edges: edges.into_iter().map(proto::Edge::from).collect(),
+        }
+    }
+}
+
+impl TryFrom<proto::FindEdgeByVertexDto> for FindEdgeByVertexDto {
+    type Error = Pyo3MongoError;
+
+    fn try_from(dto: proto::FindEdgeByVertexDto) -> Result<Self, Self::Error> {
+        match dto.orientation {
+            Some(Orientation::Source(id)) => Ok(FindEdgeByVertexDto::Source(oid(&id)?)),
+            Some(Orientation::Target(id)) => Ok(FindEdgeByVertexDto::Target(oid(&id)?)),
+            Some(Orientation::Bidirectional(id)) => {
+                Ok(FindEdgeByVertexDto::Bidirectional(oid(&id)?))
+            }
+            None => Err(Pyo3MongoError::Common("orientation is required")),
+        }
+    }
+}
+
+impl From<FindEdgeByVertexDto> for proto::FindEdgeByVertexDto {
+    fn from(dto: FindEdgeByVertexDto) -> Self {
+        let orientation = match dto {
+            FindEdgeByVertexDto::Source(id) => Orientation::Source(id.to_hex()),
+            FindEdgeByVertexDto::Target(id) => Orientation::Target(id.to_hex()),
+            FindEdgeByVertexDto::Bidirectional(id) => Orientation::Bidirectional(id.to_hex()),
+        };
+        proto::FindEdgeByVertexDto {
+            orientation: Some(orientation),
+        }
+    }
+}
+
+impl From<IndexStatus> for proto::IndexStatus {
+    fn from(i: IndexStatus) -> Self {
+        proto::IndexStatus {
+            collection: i.collection,
+            name: i.name,
+            keys: i.keys,
+            unique: i.unique,
+        }
+    }
+}
+
+impl From<GraphStats> for proto::GraphStats {
+    fn from(s: GraphStats) -> Self {
+        proto::GraphStats {
+            vertex_count: s.vertex_count,
+            edge_count: s.edge_count,
+            labels: s.labels.into_iter().collect(),
+            unlabeled_edge_count: s.unlabeled_edge_count,
+            average_degree: s.average_degree,
+            orphan_vertex_count: s.orphan_vertex_count,
+            dangling_edge_count: s.dangling_edge_count,
+            indexes: s
+                .indexes
+                .into_iter()
+                .map(proto::IndexStatus::from)
+                .collect(),
+        }
+    }
+}
+
+type RpcResult<T> = Result<Response<T>, Status>;
+
+/// server-streamed results
+pub type RpcStream<T> = Pin<Box<dyn Stream<Item = Result<T, Status>> + Send>>;
+
+// `Status` is what tonic expects, large as it is
+#[allow(clippy::result_large_err)]
+fn stream<T, M>(items: Vec<T>) -> RpcResult<RpcStream<M>>
+where
+    M: From<T> + Send + 'static,
+    T: Send + 'static,
+{
+    let items = items.into_iter().map(|i| Ok(M::from(i)));
+    Ok(Response::new(Box::pin(futures::stream::iter(items))))
+}
+
+/// `Graph` service over a `GraphStore`
+pub struct GraphRpc {
+    store: Arc<dyn GraphStore>,
+}
+
+impl GraphRpc {
+    pub fn new(store: Arc<dyn GraphStore>) -> Self {
+        GraphRpc { store }
+    }
+
+    /// the service, ready to be added to a `tonic::transport::Server`
+    pub fn into_server(self) -> GraphServer<Self> {
+        GraphServer::new(self)
+    }
+
+    async fn traversal(
+        &self,
+        req: proto::TraversalRequest,
+    ) -> Pyo3MongoResult<(Vec<Edge>, Vec<Vertex>)> {
+        self.store
+            .get_graph_from_vertex_by_label(
+                oid(&req.vertex_id)?,
+                req.label.as_deref(),
+                req.depth,
+                req.at.map(datetime).transpose()?,
+            )
+            .await
+    }
+}
+
+// items of a traversal being streamed
+type TraversalSender = mpsc::Sender<Result<proto::TraversalItem, Status>>;
+
+// false once the client is gone
+async fn send(tx: &TraversalSender, item: Item) -> bool {
+    let item = proto::TraversalItem { item: Some(item) };
+    tx.send(Ok(item)).await.is_ok()
+}
+
+// breadth-first, a level at a time: the edges of a level are sent, then the
+// vertexes they lead to, which are the next frontier. Edges & vertexes are
+// sent once, as `get_graph_from_vertex_by_label` returns them.
+async fn traverse_levels(
+    store: Arc<dyn GraphStore>,
+    start: ObjectId,
+    label: Option<String>,
+    depth: Option<i32>,
+    at: Option<DateTime>,
+    tx: TraversalSender,
+) -> Pyo3MongoResult<()> {
+    let mut visited = HashSet::new();
+    let mut sent = HashSet::new();
+    let mut frontier = vec![start];
+    let mut d = 0;
+    while !frontier.is_empty() && depth.is_none_or(|n| d <= n) {
+        let edges = store
+            .get_edges_from_vertexes_by_label(frontier, label.as_deref(), Some(0), at)
+            .await?
+            .into_iter()
+            .filter(|e| e.id.is_none_or(|id| visited.insert(id)))
+            .collect::<Vec<_>>();
+
+        let mut targets = Vec::new();
+        for e in edges {
+            if !targets.contains(&e.target) {
+                targets.push(e.target);
+            }
+            if !send(&tx, Item::Edge(e.into())).await {
+                return Ok(());
+            }
+        }
+
+        let reached = targets
+            .iter()
+            .filter(|t| sent.insert(**t))
+            .copied()
+            .collect::<Vec<_>>();
+        for v in store.get_vertexes(reached).await? {
+            if !send(&tx, Item::Vertex(v.into())).await {
+                return Ok(());
+            }
+        }
+
+        frontier = targets;
+        d += 1;
+    }
+
+    Ok(())
+}
+
+#[tonic::async_trait]
+impl Graph for GraphRpc {
+    type GetVertexesStream = RpcStream<proto::Vertex>;
+    type GetAllVertexesStream = RpcStream<proto::Vertex>;
+    type GetEdgesStream = RpcStream<proto::Edge>;
+    type GetAllEdgesStream = RpcStream<proto::Edge>;
+    type GetEdgesByVertexStream = RpcStream<proto::Edge>;
+    type TraverseStream = RpcStream<proto::TraversalItem>;
+
+    async fn create_vertex(
+        &self,
+        req: Request<proto::CreateVertexRequest>,
+    ) -> RpcResult<proto::Vertex> {
+        let req = req.into_inner();
+        let vertex = self.store.create_vertex(VertexDto::new(&req.name)).await?;
+        Ok(Response::new(vertex.into()))
+    }
+
+    async fn get_vertex(&self, req: Request<proto::Id>) -> RpcResult<proto::Vertex> {
+        let vertex = self.store.get_vertex(oid(&req.into_inner().id)?).await?;
+        Ok(Response::new(vertex.into()))
+    }
+
+    async fn get_vertex_by_name(&self, req: Request<proto::Name>) -> RpcResult<proto::Vertex> {
+        let vertex = self
+            .store
+            .get_vertex_by_name(&req.into_inner().name)
+            .await?;
+        Ok(Response::new(vertex.into()))
+    }
+
+    async fn get_or_create_vertex(&self, req: Request<proto::Name>) -> RpcResult<proto::Vertex> {
+        let req = req.into_inner();
+        let vertex = self
+            .store
+            .get_or_create_vertex(VertexDto::new(&req.name))
+            .await?;
+        Ok(Response::new(vertex.into()))
+    }
+
+    async fn get_vertexes(&self, req: Request<proto::Ids>) -> RpcResult<Self::GetVertexesStream> {
+        stream(
+            self.store
+                .get_vertexes(oids(&req.into_inner().ids)?)
+                .await?,
+        )
+    }
+
+    async fn get_all_vertexes(&self, _: Request<()>) -> RpcResult<Self::GetAllVertexesStream> {
+        stream(self.store.get_all_vertexes().await?)
+    }
+
+    async fn update_vertex(
+        &self,
+        req: Request<proto::UpdateVertexRequest>,
+    ) -> RpcResult<proto::Vertex> {
+        let req = req.into_inner();
+        let vertex = self
+            .store
+            .update_vertex(oid(&req.id)?, VertexDto::new(&req.name))
+            .await?;
+        Ok(Response::new(vertex.into()))
+    }
+
+    async fn delete_vertex(&self, req: Request<proto::Id>) -> RpcResult<()> {
+        self.store.delete_vertex(oid(&req.into_inner().id)?).await?;
+        Ok(Response::new(()))
+    }
+
+    async fn create_edge(&self, req: Request<proto::Edge>) -> RpcResult<proto::Edge> {
+        let req = req.into_inner();
+        let edge = self.store.create_edge(req.dto()?).await?;
+        Ok(Response::new(edge.into()))
+    }
+
+    async fn get_edge(&self, req: Request<proto::Id>) -> RpcResult<proto::Edge> {
+        let edge = self.store.get_edge(oid(&req.into_inner().id)?).await?;
+        Ok(Response::new(edge.into()))
+    }
+
+    async fn get_edges(&self, req: Request<proto::Ids>) -> RpcResult<Self::GetEdgesStream> {
+        stream(self.store.get_edges(oids(&req.into_inner().ids)?).await?)
+    }
+
+    async fn get_all_edges(&self, _: Request<()>) -> RpcResult<Self::GetAllEdgesStream> {
+        stream(self.store.get_all_edges().await?)
+    }
+
+    async fn update_edge(&self, req: Request<proto::UpdateEdgeRequest>) -> RpcResult<proto::Edge> {
+        let req = req.into_inner();
+        let edge = req.edge.ok_or(Pyo3MongoError::Common("edge is required"))?;
+        let edge = self.store.update_edge(oid(&req.id)?, edge.dto()?).await?;
+        Ok(Response::new(edge.into()))
+    }
+
+    async fn delete_edge(&self, req: Request<proto::Id>) -> RpcResult<()> {
+        self.store.delete_edge(oid(&req.into_inner().id)?).await?;
+        Ok(Response::new(()))
+    }
+
+    async fn delete_edges(&self, req: Request<proto::Ids>) -> RpcResult<()> {
+        self.store
+            .delete_edges(oids(&req.into_inner().ids)?)
+            .await?;
+        Ok(Response::new(()))
+    }
+
+    async fn get_edges_by_vertex(
+        &self,
+        req: Request<proto::FindEdgeByVertexDto>,
+    ) -> RpcResult<Self::GetEdgesByVertexStream> {
+        let dto = FindEdgeByVertexDto::try_from(req.into_inner())?;
+        stream(self.store.get_edges_by_vertex(dto).await?)
+    }
+
+    async fn traverse(
+        &self,
+        req: Request<proto::TraversalRequest>,
+    ) -> RpcResult<Self::TraverseStream> {
+        let req = req.into_inner();
+        let start = oid(&req.vertex_id)?;
+        let at = req.at.map(datetime).transpose()?;
+        check_depth(req.depth)?;
+
+        // streamed as levels are fetched, rather than once all of them are
+        let (tx, rx) = mpsc::channel(64);
+        let store = self.store.clone();
+        tokio::spawn(async move {
+            let res = traverse_levels(store, start, req.label, req.depth, at, tx.clone()).await;
+            if let Err(e) = res {
+                // the client may be gone already
+                let _ = tx.send(Err(e.into())).await;
+            }
+        });
+
+        Ok(Response::new(Box::pin(ReceiverStream::new(rx))))
+    }
+
+    async fn get_graph(
+        &self,
+        req: Request<proto::TraversalRequest>,
+    ) -> RpcResult<proto::GraphOutput> {
+        let (edges, vertexes) = self.traversal(req.into_inner()).await?;
+        Ok(Response::new(proto::GraphOutput::new(vertexes, edges)))
+    }
+
+    async fn insert_graph(
+        &self,
+        req: Request<proto::GraphOutput>,
+    ) -> RpcResult<proto::GraphOutput> {
+        let req = req.into_inner();
+        let vertexes = req
+            .vertexes
+            .into_iter()
+            .map(Vertex::try_from)
+            .collect::<Pyo3MongoResult<Vec<_>>>()?;
+        let edges = req
+            .edges
+            .into_iter()
+            .map(Edge::try_from)
+            .collect::<Pyo3MongoResult<Vec<_>>>()?;
+        let (edges, vertexes) = self.store.insert_graph(vertexes, edges).await?;
+        Ok(Response::new(proto::GraphOutput::new(vertexes, edges)))
+    }
+
+    async fn truncate_all(&self, _: Request<()>) -> RpcResult<()> {
+        self.store.truncate_all().await?;
+        Ok(Response::new(()))
+    }
+
+    async fn stats(&self, _: Request<()>) -> RpcResult<proto::GraphStats> {
+        Ok(Response::new(self.store.stats().await?.into()))
+    }
+}
+
+#[cfg(test)]
+mod test_grpc {
+    use futures::TryStreamExt;
+    use tonic::transport::server::TcpIncoming;
+    use tonic::transport::{Channel, Server};
+
+    use super::proto::graph_client::GraphClient;
+    use super::*;
+    use crate::harness::TestGraph;
+    use crate::store::MemoryStore;
+
+    // a server over `store` on a free port, and a client of it
+    async fn serve(store: Arc<dyn GraphStore>) -> GraphClient<Channel> {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let incoming = TcpIncoming::from_listener(listener, true, None).unwrap();
+        let rpc = GraphRpc::new(store);
+        tokio::spawn(
+            Server::builder()
+                .add_service(rpc.into_server())
+                .serve_with_incoming(incoming),
+        );
+
+        GraphClient::connect(format!("http://{}", addr))
+            .await
+            .unwrap()
+    }
+
+    async fn client() -> GraphClient<Channel> {
+        serve(Arc::new(MemoryStore::new())).await
+    }
+
+    // a `MemoryStore`, and a service over a `TestGraph` if a test server is
+    // set, which is kept along for its category to be dropped with it
+    async fn stores(name: &str) -> Vec<(Arc<dyn GraphStore>, Option<TestGraph>)> {
+        let mut stores: Vec<(Arc<dyn GraphStore>, Option<TestGraph>)> =
+            vec![(Arc::new(MemoryStore::new()), None)];
+        if let Some(graph) = TestGraph::connect(name).await {
+            let service = graph.service().await.unwrap();
+            stores.push((Arc::new(service), Some(graph)));
+        }
+        stores
+    }
+
+    fn vertex_request(name: &str) -> proto::CreateVertexRequest {
+        proto::CreateVertexRequest {
+            name: name.to_string(),
+        }
+    }
+
+    fn name(name: &str) -> proto::Name {
+        proto::Name {
+            name: name.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_conversion() {
+        let edge = Edge {
+            id: Some(ObjectId::new()),
+            source: ObjectId::new(),
+            target: ObjectId::new(),
+            weight: Some(1.5),
+            label: Some("knows".to_string()),
+            valid_from: Some(DateTime::from_millis(-1500)),
+            valid_to: Some(DateTime::from_millis(1_650_000_000_123)),
+        };
+        let message = proto::Edge::from(edge.clone());
+        assert_eq!(message.valid_from.as_ref().unwrap().seconds, -2);
+        assert_eq!(Edge::try_from(message).unwrap(), edge);
+
+        let vertex = Vertex {
+            id: None,
+            name: "node-1".to_string(),
+            properties: Some(mongodb::bson::doc! {"age": 3, "tags": ["a"]}),
+        };
+        let message = proto::Vertex::from(vertex.clone());
+        assert!(message.id.is_empty());
+        assert_eq!(Vertex::try_from(message).unwrap(), vertex);
+
+        let dto = proto::FindEdgeByVertexDto { orientation: None };
+        assert!(FindEdgeByVertexDto::try_from(dto).is_err());
+
+        let ts = prost_types::Timestamp {
+            seconds: i64::MAX / 10,
+            nanos: 0,
+        };
+        assert!(datetime(ts).is_err());
+    }
+
+    #[test]
+    fn test_status() {
+        let retried = |source| Pyo3MongoError::Retried {
+            attempts: 3,
+            source: Box::new(source),
+        };
+        let reset = std::io::Error::new(std::io::ErrorKind::ConnectionReset, "reset");
+        let network = mongodb::error::ErrorKind::Io(Arc::new(reset));
+
+        let status = Status::from(retried(Pyo3MongoError::VertexNotFound));
+        assert_eq!(status.code(), Code::NotFound);
+        let status = Status::from(retried(Pyo3MongoError::Mongo(network.into())));
+        assert_eq!(status.code(), Code::Unavailable);
+    }
+
+    #[tokio::test]
+    async fn test_rpc() {
+        let mut client = client().await;
+
+        let node1 = client
+            .create_vertex(vertex_request("node-1"))
+            .await
+            .unwrap()
+            .into_inner();
+        let node2 = client
+            .get_or_create_vertex(name("node-2"))
+            .await
+            .unwrap()
+            .into_inner();
+        let edge = client
+            .create_edge(proto::Edge {
+                source: node1.id.clone(),
+                target: node2.id.clone(),
+                label: Some("knows".to_string()),
+                ..Default::default()
+            })
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(!edge.id.is_empty());
+
+        let found = client
+            .get_vertex_by_name(name("node-2"))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(found, node2);
+
+        let dto = FindEdgeByVertexDto::Target(oid(&node2.id).unwrap());
+        let edges = client
+            .get_edges_by_vertex(proto::FindEdgeByVertexDto::from(dto))
+            .await
+            .unwrap()
+            .into_inner()
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+        assert_eq!(edges, vec![edge.clone()]);
+
+        let items = client
+            .traverse(proto::TraversalRequest {
+                vertex_id: node1.id.clone(),
+                ..Default::default()
+            })
+            .await
+            .unwrap()
+            .into_inner()
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|i| i.item.unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(items, vec![Item::Edge(edge), Item::Vertex(node2)]);
+
+        let stats = client.stats(()).await.unwrap().into_inner();
+        assert_eq!((stats.vertex_count, stats.edge_count), (2, 1));
+        assert_eq!(stats.labels.get("knows"), Some(&1));
+    }
+
+    #[tokio::test]
+    async fn test_traverse() {
+        let mut client = client().await;
+
+        let mut nodes = Vec::new();
+        for name in ["a", "b", "c"] {
+            let node = client
+                .create_vertex(vertex_request(name))
+                .await
+                .unwrap()
+                .into_inner();
+            nodes.push(node);
+        }
+        // a -> b -> c -> a
+        let mut edges = Vec::new();
+        for (s, t) in [(0, 1), (1, 2), (2, 0)] {
+            let edge = client
+                .create_edge(proto::Edge {
+                    source: nodes[s].id.clone(),
+                    target: nodes[t].id.clone(),
+                    ..Default::default()
+                })
+                .await
+                .unwrap()
+                .into_inner();
+            edges.push(edge);
+        }
+
+        let traverse = |depth| {
+            let req = proto::TraversalRequest {
+                vertex_id: nodes[0].id.clone(),
+                depth,
+                ..Default::default()
+            };
+            let mut client = client.clone();
+            async move {
+                client
+                    .traverse(req)
+                    .await
+                    .unwrap()
+                    .into_inner()
+                    .try_collect::<Vec<_>>()
+                    .await
+                    .unwrap()
+                    .into_iter()
+                    .map(|i| i.item.unwrap())
+                    .collect::<Vec<_>>()
+            }
+        };
+
+        // a level at a time, each edge & vertex once
+        let items = traverse(None).await;
+        let expected = (0..3)
+            .flat_map(|i| {
+                [
+                    Item::Edge(edges[i].clone()),
+                    Item::Vertex(nodes[(i + 1) % 3].clone()),
+                ]
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(items, expected);
+        assert_eq!(traverse(Some(0)).await, expected[..2]);
+
+        let status = client
+            .traverse(proto::TraversalRequest {
+                vertex_id: nodes[0].id.clone(),
+                depth: Some(-1),
+                ..Default::default()
+            })
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), Code::InvalidArgument);
+    }
+
+    #[tokio::test]
+    async fn test_rpc_errors() {
+        for (store, _graph) in stores("rpc_errors").await {
+            rpc_errors(serve(store).await).await;
+        }
+    }
+
+    async fn rpc_errors(mut client: GraphClient<Channel>) {
+        let status = client
+            .get_vertex(proto::Id {
+                id: ObjectId::new().to_hex(),
+            })
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), Code::NotFound);
+        let status = client
+            .get_edge(proto::Id {
+                id: ObjectId::new().to_hex(),
+            })
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), Code::NotFound);
+
+        let status = client
+            .get_vertex(proto::Id {
+                id: "not-an-id".to_string(),
+            })
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), Code::InvalidArgument);
+
+        let status = client
+            .get_edges_by_vertex(proto::FindEdgeByVertexDto { orientation: None })
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), Code::InvalidArgument);
+
+        let inserted = client
+            .insert_graph(proto::GraphOutput {
+                vertexes: vec![proto::Vertex {
+                    name: "node-1".to_string(),
+                    properties: r#"{"age": 3}"#.to_string(),
+                    ..Default::default()
+                }],
+                edges: vec![],
+            })
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(inserted.vertexes.len(), 1);
+        assert!(!inserted.vertexes[0].id.is_empty());
+
+        let all = client
+            .get_all_vertexes(())
+            .await
+            .unwrap()
+            .into_inner()
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+        assert_eq!(all, inserted.vertexes);
+    }
+}
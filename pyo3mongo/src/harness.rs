@@ -0,0 +1,173 @@
+//! Test harness
+//!
+//! Mongo-backed tests work on a `TestGraph`: a `GraphService` over a category
+//! of its own, so that tests can run in parallel, whose collections are dropped
+//! once it goes out of scope.
+//!
+//! The server is given by `P3M_TEST_URI` (and `P3M_TEST_DB`, "graph" by
+//! default); Mongo-backed tests are skipped if it is not set.
+//!
+//! `store_suite!` runs generic cases against both a `MemoryStore` and a
+//! `TestGraph`.
+//!
+//! Public (though hidden) so that tests of the bins can use it too.
+
+use std::ops::Deref;
+
+use mongodb::bson::oid::ObjectId;
+use mongodb::bson::{doc, Document};
+use mongodb::Client;
+
+use crate::acl::AccessPolicy;
+use crate::cache::CacheOptions;
+use crate::config::MongoConfig;
+use crate::retry::RetryPolicy;
+use crate::service::GraphService;
+use crate::Pyo3MongoResult;
+
+pub const URI_VAR: &str = "P3M_TEST_URI";
+pub const DB_VAR: &str = "P3M_TEST_DB";
+
+/// config of the test server, `None` (and a notice) if not set
+pub fn config() -> Option<MongoConfig> {
+    match std::env::var(URI_VAR) {
+        Ok(uri) => {
+            let db = std::env::var(DB_VAR).unwrap_or_else(|_| "graph".to_owned());
+            Some(MongoConfig::new(&uri, &db))
+        }
+        Err(_) => {
+            eprintln!("{} is not set, Mongo-backed test skipped", URI_VAR);
+            None
+        }
+    }
+}
+
+/// a `GraphService` over a unique category, cleaned up on drop
+pub struct TestGraph {
+    service: GraphService,
+    config: MongoConfig,
+    cat: String,
+}
+
+impl TestGraph {
+    /// `None` if no test server is set
+    pub async fn connect(name: &str) -> Option<Self> {
+        let config = config()?;
+        let cat = format!("test_{}_{}", name, ObjectId::new());
+        let service = GraphService::with_config(&config, &cat).await.unwrap();
+
+        Some(TestGraph {
+            service,
+            config,
+            cat,
+        })
+    }
+
+    pub fn category(&self) -> &str {
+        &self.cat
+    }
+
+    /// another service over the same category, on behalf of the caller of
+    /// `api_key`
+    pub async fn with_access(
+        &self,
+        policy: &AccessPolicy,
+        api_key: &str,
+    ) -> Pyo3MongoResult<GraphService> {
+        GraphService::with_access(&self.config, &self.cat, policy, api_key).await
+    }
+
+    /// another service over the same category, e.g. to be shared
+    pub async fn service(&self) -> Pyo3MongoResult<GraphService> {
+        GraphService::with_config(&self.config, &self.cat).await
+    }
+
+    /// another service over the same category, with a cache
+    pub async fn cached(&self, options: CacheOptions) -> Pyo3MongoResult<GraphService> {
+        Ok(GraphService::with_config(&self.config, &self.cat)
+            .await?
+            .with_cache(options))
+    }
+
+    /// another service over the same category, with a retry policy
+    pub async fn retried(&self, policy: RetryPolicy) -> Pyo3MongoResult<GraphService> {
+        Ok(GraphService::with_config(&self.config, &self.cat)
+            .await?
+            .with_retry(policy))
+    }
+}
+
+impl Deref for TestGraph {
+    type Target = GraphService;
+
+    fn deref(&self) -> &Self::Target {
+        &self.service
+    }
+}
+
+impl Drop for TestGraph {
+    fn drop(&mut self) {
+        let config = self.config.clone();
+        let cat = self.cat.clone();
+
+        // `drop` cannot be async, nor block on the test's runtime: clean up in a
+        // thread (and a runtime) of its own
+        let res = std::thread::spawn(move || {
+            tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .unwrap()
+                .block_on(drop_category(&config, &cat))
+        })
+        .join();
+
+        if let Ok(Err(e)) = res {
+            eprintln!("failed to drop category {}: {}", self.cat, e);
+        }
+    }
+}
+
+/// drop every collection of a category: `{cat}_vertex`, `{cat}_edge`, etc.
+async fn drop_category(config: &MongoConfig, cat: &str) -> Pyo3MongoResult<()> {
+    let client = Client::with_options(config.client_options().await?)?;
+    let db = client.database(&config.database);
+
+    let filter = doc! {"name": {"$regex": format!("^{}_", cat)}};
+    for name in db.list_collection_names(filter).await? {
+        db.collection::<Document>(&name).drop(None).await?;
+    }
+
+    Ok(())
+}
+
+/// generates, for each generic case `async fn case(store: &impl GraphStore)`,
+/// a test against a `MemoryStore` (`memory::case`) and one against a
+/// `TestGraph` (`mongo::case`, skipped if no server is set)
+#[cfg(test)]
+macro_rules! store_suite {
+    ($($case:ident),* $(,)?) => {
+        mod memory {
+            $(
+                #[tokio::test]
+                async fn $case() {
+                    super::$case(&$crate::store::MemoryStore::new()).await;
+                }
+            )*
+        }
+
+        mod mongo {
+            $(
+                #[tokio::test]
+                async fn $case() {
+                    let name = stringify!($case);
+                    if let Some(gs) = $crate::harness::TestGraph::connect(name).await {
+                        super::$case(&*gs).await;
+                    }
+                }
+            )*
+        }
+    };
+}
+
+#[cfg(test)]
+pub(crate) use store_suite;
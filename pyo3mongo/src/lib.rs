@@ -1,12 +1,34 @@
 //! Pyo3Mongo
 
+pub mod acl;
+pub mod cache;
+pub mod centrality;
+pub mod config;
 pub mod db;
+pub mod diff;
+pub mod grpc;
+#[doc(hidden)]
+pub mod harness;
 pub mod model;
 pub mod package;
+pub mod query;
+pub mod retry;
+pub mod sampling;
 pub mod service;
+pub mod store;
+pub mod telemetry;
+pub mod weights;
 
+pub use acl::{AccessPolicy, Operation};
+pub use cache::{CacheOptions, CacheStats};
+pub use config::MongoConfig;
+pub use diff::{DiffKey, GraphDiff};
 pub use model::*;
+pub use retry::RetryPolicy;
+pub use sampling::{Sample, SnowballOptions, WalkOptions, Walks};
 pub use service::GraphService;
+pub use store::{GraphStore, MemoryStore};
+pub use weights::{AdjacencyMatrix, LabelWeights, VertexWeight};
 
 use thiserror::Error;
 
@@ -17,12 +39,76 @@ pub enum Pyo3MongoError {
     #[error("common error {0}")]
     Common(&'static str),
 
+    #[error("vertex not found")]
+    VertexNotFound,
+
+    #[error("edge not found")]
+    EdgeNotFound,
+
+    #[error("hyperedge not found")]
+    HyperEdgeNotFound,
+
+    #[error("query error {0}")]
+    Query(String),
+
+    #[error("config error {0}")]
+    Config(String),
+
+    #[error("conflict error {0}")]
+    Conflict(String),
+
+    #[error("write error {0}")]
+    Write(String),
+
+    #[error("permission denied {0}")]
+    Denied(String),
+
+    #[error("{source} (after {attempts} attempts)")]
+    Retried {
+        attempts: u32,
+        source: Box<Pyo3MongoError>,
+    },
+
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+
     #[error(transparent)]
     Mongo(#[from] mongodb::error::Error),
 
     #[error(transparent)]
     De(#[from] bson::de::Error),
 
+    #[error(transparent)]
+    Ser(#[from] bson::ser::Error),
+
     #[error(transparent)]
     Oid(#[from] bson::oid::Error),
+
+    #[error(transparent)]
+    Transport(#[from] tonic::transport::Error),
+}
+
+/// Ctrl-C, or SIGTERM on unix, for servers to shut down gracefully
+pub async fn shutdown_signal() {
+    let ctrl_c = async {
+        tokio::signal::ctrl_c().await.unwrap();
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .unwrap()
+            .recv()
+            .await;
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
 }
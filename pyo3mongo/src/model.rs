@@ -8,10 +8,15 @@
 //! 1. Create two `Vertex`s `v1` and `v2`
 //! 2. Create an `Edge` that connects `v1` and `v2`
 
-use mongodb::bson::{self, oid::ObjectId, Document};
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+use mongodb::bson::{self, doc, oid::ObjectId, Bson, DateTime, Document};
 use pyo3::prelude::*;
 use serde::{Deserialize, Serialize};
 
+use crate::Pyo3MongoError;
+
 #[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Debug)]
 pub struct PureId {
     #[serde(rename = "_id")]
@@ -19,7 +24,7 @@ pub struct PureId {
 }
 
 /// edge between two vertices
-#[pyclass]
+#[pyclass(module = "p3m")]
 #[derive(Serialize, Deserialize, PartialEq, Clone, Debug)]
 pub struct Edge {
     #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
@@ -28,15 +33,85 @@ pub struct Edge {
     pub target: ObjectId,
     pub weight: Option<f64>,
     pub label: Option<String>,
+    /// the edge is valid from this instant on (inclusive), since ever if absent
+    #[serde(default)]
+    pub valid_from: Option<DateTime>,
+    /// the edge is valid until this instant (exclusive), forever if absent
+    #[serde(default)]
+    pub valid_to: Option<DateTime>,
+}
+
+impl Edge {
+    /// whether `at` is within `[valid_from, valid_to)`
+    pub fn is_valid_at(&self, at: DateTime) -> bool {
+        self.valid_from.is_none_or(|from| from <= at) && self.valid_to.is_none_or(|to| at < to)
+    }
+}
+
+/// a validity interval is empty unless `valid_from` precedes `valid_to`
+pub fn check_validity(
+    valid_from: Option<DateTime>,
+    valid_to: Option<DateTime>,
+) -> Result<(), Pyo3MongoError> {
+    match (valid_from, valid_to) {
+        (Some(from), Some(to)) if from >= to => {
+            Err(Pyo3MongoError::Common("valid_from must precede valid_to"))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// `maxDepth` of `$graphLookup` must not be negative
+pub fn check_depth(depth: Option<i32>) -> Result<(), Pyo3MongoError> {
+    match depth {
+        Some(n) if n < 0 => Err(Pyo3MongoError::Common("depth must not be negative")),
+        _ => Ok(()),
+    }
 }
 
 /// vertex
-#[pyclass]
-#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Debug)]
+#[pyclass(module = "p3m")]
+#[derive(Serialize, Deserialize, PartialEq, Clone, Debug)]
 pub struct Vertex {
     #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
     pub id: Option<ObjectId>,
     pub name: String,
+    // skipped if none, so that `$set` a vertex never wipes out its properties
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub properties: Option<Document>,
+}
+
+/// a vertex taking part in a hyperedge, under a role (e.g. "buyer")
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Debug)]
+pub struct Member {
+    pub vertex: ObjectId,
+    pub role: String,
+}
+
+/// relationship among any number of vertexes, e.g. a transaction with a
+/// buyer, a seller & a broker. It connects each of its members to the others.
+#[pyclass(module = "p3m")]
+#[derive(Serialize, Deserialize, PartialEq, Clone, Debug)]
+pub struct HyperEdge {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub label: Option<String>,
+    pub members: Vec<Member>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub properties: Option<Document>,
+}
+
+impl HyperEdge {
+    /// vertexes of the members, in order, without duplicates
+    pub fn vertexes(&self) -> Vec<ObjectId> {
+        let mut res = Vec::new();
+        for m in &self.members {
+            if !res.contains(&m.vertex) {
+                res.push(m.vertex);
+            }
+        }
+        res
+    }
 }
 
 // required by Mongo query
@@ -53,6 +128,150 @@ impl From<&Vertex> for Document {
     }
 }
 
+/// `$jsonSchema` of a model, used as a collection validator (see
+/// `GraphService::install_validators`). It is derived from the fields of the
+/// model by `json_schema!`: an `Option` field may be missing or null, extra
+/// fields are allowed.
+pub trait JsonSchema {
+    fn json_schema() -> Document;
+}
+
+/// schema of a field of a given type, for `json_schema!`
+pub trait BsonType {
+    /// whether a document must have the field
+    const REQUIRED: bool = true;
+
+    fn bson_type() -> Document;
+}
+
+impl BsonType for ObjectId {
+    fn bson_type() -> Document {
+        doc! {"bsonType": "objectId"}
+    }
+}
+
+impl BsonType for String {
+    fn bson_type() -> Document {
+        doc! {"bsonType": "string"}
+    }
+}
+
+impl BsonType for f64 {
+    // integers are accepted by `f64`'s deserializer
+    fn bson_type() -> Document {
+        doc! {"bsonType": ["double", "int", "long"]}
+    }
+}
+
+impl BsonType for DateTime {
+    fn bson_type() -> Document {
+        doc! {"bsonType": "date"}
+    }
+}
+
+impl BsonType for Document {
+    fn bson_type() -> Document {
+        doc! {"bsonType": "object"}
+    }
+}
+
+impl<T: BsonType> BsonType for Option<T> {
+    const REQUIRED: bool = false;
+
+    fn bson_type() -> Document {
+        let mut schema = T::bson_type();
+        let mut types = match schema.remove("bsonType") {
+            Some(Bson::Array(types)) => types,
+            Some(t) => vec![t],
+            None => Vec::new(),
+        };
+        types.push("null".into());
+        schema.insert("bsonType", types);
+        schema
+    }
+}
+
+impl<T: BsonType> BsonType for Vec<T> {
+    fn bson_type() -> Document {
+        doc! {"bsonType": "array", "items": T::bson_type()}
+    }
+}
+
+impl BsonType for Member {
+    fn bson_type() -> Document {
+        Self::json_schema()
+    }
+}
+
+/// implement `JsonSchema` for a model from the list of all its fields, each
+/// with its serde name (if renamed), its type & extra constraints (if any).
+/// The list is checked against the model, so that they cannot diverge.
+macro_rules! json_schema {
+    (@key $field:ident) => {
+        stringify!($field)
+    };
+    (@key $field:ident $key:literal) => {
+        $key
+    };
+    ($model:ident { $($field:ident $(as $key:literal)?: $ty:ty $(where $extra:tt)?),* $(,)? }) => {
+        impl JsonSchema for $model {
+            fn json_schema() -> Document {
+                // every field is listed, with its type
+                let _ = |m: $model| {
+                    let $model { $($field),* } = m;
+                    $(let _: $ty = $field;)*
+                };
+
+                let mut required = Vec::<Bson>::new();
+                let mut properties = Document::new();
+                $(
+                    let key = json_schema!(@key $field $($key)?);
+                    if <$ty as BsonType>::REQUIRED {
+                        required.push(key.into());
+                    }
+                    #[allow(unused_mut)]
+                    let mut schema = <$ty as BsonType>::bson_type();
+                    $(schema.extend(doc! $extra);)?
+                    properties.insert(key, schema);
+                )*
+                doc! {
+                    "bsonType": "object",
+                    "required": required,
+                    "properties": properties,
+                }
+            }
+        }
+    };
+}
+
+json_schema!(Vertex {
+    id as "_id": Option<ObjectId>,
+    name: String,
+    properties: Option<Document>,
+});
+
+json_schema!(Edge {
+    id as "_id": Option<ObjectId>,
+    source: ObjectId,
+    target: ObjectId,
+    weight: Option<f64>,
+    label: Option<String>,
+    valid_from: Option<DateTime>,
+    valid_to: Option<DateTime>,
+});
+
+json_schema!(Member {
+    vertex: ObjectId,
+    role: String,
+});
+
+json_schema!(HyperEdge {
+    id as "_id": Option<ObjectId>,
+    label: Option<String>,
+    members: Vec<Member> where {"minItems": 2},
+    properties: Option<Document>,
+});
+
 /// DTO for `Edge`
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct EdgeDto<'a> {
@@ -60,6 +279,8 @@ pub struct EdgeDto<'a> {
     pub target: ObjectId,
     pub weight: Option<f64>,
     pub label: Option<&'a str>,
+    pub valid_from: Option<DateTime>,
+    pub valid_to: Option<DateTime>,
 }
 
 impl<'a> EdgeDto<'a> {
@@ -74,8 +295,21 @@ impl<'a> EdgeDto<'a> {
             target,
             weight,
             label,
+            valid_from: None,
+            valid_to: None,
         }
     }
+
+    /// restrict the edge to `[valid_from, valid_to)`
+    pub fn valid_between(
+        mut self,
+        valid_from: Option<DateTime>,
+        valid_to: Option<DateTime>,
+    ) -> Self {
+        self.valid_from = valid_from;
+        self.valid_to = valid_to;
+        self
+    }
 }
 
 impl<'a> From<EdgeDto<'a>> for Edge {
@@ -86,6 +320,50 @@ impl<'a> From<EdgeDto<'a>> for Edge {
             target: source.target,
             weight: source.weight,
             label: source.label.map(str::to_string),
+            valid_from: source.valid_from,
+            valid_to: source.valid_to,
+        }
+    }
+}
+
+/// DTO for `HyperEdge`
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct HyperEdgeDto<'a> {
+    pub label: Option<&'a str>,
+    /// `(vertex, role)`
+    pub members: Vec<(ObjectId, &'a str)>,
+    pub properties: Option<Document>,
+}
+
+impl<'a> HyperEdgeDto<'a> {
+    pub fn new(members: Vec<(ObjectId, &'a str)>, label: Option<&'a str>) -> Self {
+        HyperEdgeDto {
+            label,
+            members,
+            properties: None,
+        }
+    }
+
+    pub fn with_properties(mut self, properties: Document) -> Self {
+        self.properties = Some(properties);
+        self
+    }
+}
+
+impl<'a> From<HyperEdgeDto<'a>> for HyperEdge {
+    fn from(source: HyperEdgeDto<'a>) -> Self {
+        HyperEdge {
+            id: None,
+            label: source.label.map(str::to_string),
+            members: source
+                .members
+                .into_iter()
+                .map(|(vertex, role)| Member {
+                    vertex,
+                    role: role.to_owned(),
+                })
+                .collect(),
+            properties: source.properties,
         }
     }
 }
@@ -107,6 +385,7 @@ impl<'a> From<VertexDto<'a>> for Vertex {
         Vertex {
             id: None,
             name: source.name.to_string(),
+            properties: None,
         }
     }
 }
@@ -128,3 +407,344 @@ impl FindEdgeByVertexDto {
         }
     }
 }
+
+/// which part of a category an algorithm is applied on
+#[derive(Debug, Clone, Copy)]
+pub enum GraphScope<'a> {
+    /// all vertexes and edges of the category
+    Category,
+    /// the result of `GraphService::get_edges_from_vertex_by_label`
+    Traversal {
+        vertex_id: ObjectId,
+        label: Option<&'a str>,
+        depth: Option<i32>,
+        at: Option<DateTime>,
+    },
+}
+
+/// parameters of `GraphService::neighborhood`
+#[derive(Debug, Clone, Copy)]
+pub struct NeighborhoodOptions<'a> {
+    /// number of hops, following outgoing edges
+    pub hops: u32,
+    pub label: Option<&'a str>,
+    /// only edges valid at this instant are followed
+    pub at: Option<DateTime>,
+    /// max number of edges kept at each hop, unlimited if absent
+    pub per_hop_limit: Option<usize>,
+    /// max number of edges kept overall, unlimited if absent
+    pub total_limit: Option<usize>,
+}
+
+impl<'a> Default for NeighborhoodOptions<'a> {
+    fn default() -> Self {
+        NeighborhoodOptions {
+            hops: 1,
+            label: None,
+            at: None,
+            per_hop_limit: Some(1_000),
+            total_limit: Some(10_000),
+        }
+    }
+}
+
+impl<'a> NeighborhoodOptions<'a> {
+    /// max number of edges to keep at the next hop, given how many have been kept
+    pub fn cap(&self, kept: usize) -> Option<usize> {
+        let remaining = self.total_limit.map(|t| t.saturating_sub(kept));
+        match (self.per_hop_limit, remaining) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        }
+    }
+}
+
+/// result of `GraphService::neighborhood`
+#[pyclass(module = "p3m")]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct Neighborhood {
+    /// vertexes reached, the starting one excluded
+    #[pyo3(get)]
+    pub vertexes: Vec<Vertex>,
+    #[pyo3(get)]
+    pub edges: Vec<Edge>,
+    /// number of edges found at each hop, before any limit applied
+    #[pyo3(get)]
+    pub found: Vec<u64>,
+    /// number of edges kept at each hop
+    #[pyo3(get)]
+    pub kept: Vec<u64>,
+    /// whether edges have been left out because of a limit
+    #[pyo3(get)]
+    pub truncated: bool,
+}
+
+/// an index of `${cat}_vertex` or `${cat}_edge`
+#[pyclass(module = "p3m")]
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct IndexStatus {
+    #[pyo3(get)]
+    pub collection: String,
+    #[pyo3(get)]
+    pub name: String,
+    /// indexed fields, in order
+    #[pyo3(get)]
+    pub keys: Vec<String>,
+    #[pyo3(get)]
+    pub unique: bool,
+}
+
+/// statistics of a category
+#[pyclass(module = "p3m")]
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct GraphStats {
+    #[pyo3(get)]
+    pub vertex_count: u64,
+    #[pyo3(get)]
+    pub edge_count: u64,
+    /// number of edges of each label
+    #[pyo3(get)]
+    pub labels: BTreeMap<String, u64>,
+    #[pyo3(get)]
+    pub unlabeled_edge_count: u64,
+    /// in-degree plus out-degree, averaged over vertexes
+    #[pyo3(get)]
+    pub average_degree: f64,
+    /// vertexes without any edge
+    #[pyo3(get)]
+    pub orphan_vertex_count: u64,
+    /// edges whose source or target vertex does not exist
+    #[pyo3(get)]
+    pub dangling_edge_count: u64,
+    #[pyo3(get)]
+    pub indexes: Vec<IndexStatus>,
+}
+
+/// offenders found by `GraphService::check_integrity`, ids are kept as raw bson
+/// values since a document written directly to Mongo may not have an ObjectId
+#[pyclass(module = "p3m")]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct IntegrityReport {
+    /// edges whose source or target vertex does not exist
+    pub dangling_edges: Vec<Bson>,
+    /// edges sharing the same source, target, label & validity, the first one of each
+    /// group is the one to keep
+    pub duplicate_edges: Vec<Vec<Bson>>,
+    /// edges whose source or target is not an ObjectId
+    pub invalid_edges: Vec<Bson>,
+    /// vertexes whose id is not an ObjectId
+    pub invalid_vertexes: Vec<Bson>,
+    /// hyperedges with less than two members, or a member whose vertex does
+    /// not exist
+    #[serde(default)]
+    pub dangling_hyperedges: Vec<Bson>,
+}
+
+impl IntegrityReport {
+    pub fn is_clean(&self) -> bool {
+        self.dangling_edges.is_empty()
+            && self.duplicate_edges.is_empty()
+            && self.invalid_edges.is_empty()
+            && self.invalid_vertexes.is_empty()
+            && self.dangling_hyperedges.is_empty()
+    }
+}
+
+/// documents which do not conform to their model's `JsonSchema`, found by
+/// `GraphService::validate`
+#[pyclass(module = "p3m")]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct ValidationReport {
+    /// `_id`s of non-conforming vertexes
+    pub vertexes: Vec<Bson>,
+    /// `_id`s of non-conforming edges
+    pub edges: Vec<Bson>,
+    /// `_id`s of non-conforming hyperedges
+    #[serde(default)]
+    pub hyperedges: Vec<Bson>,
+}
+
+impl ValidationReport {
+    pub fn is_valid(&self) -> bool {
+        self.vertexes.is_empty() && self.edges.is_empty() && self.hyperedges.is_empty()
+    }
+}
+
+/// what to do with offenders of an `IntegrityReport`
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RepairAction {
+    /// delete them
+    Delete,
+    /// move them into `${cat}_vertex_quarantine`, `${cat}_edge_quarantine` &
+    /// `${cat}_hyperedge_quarantine`
+    Quarantine,
+}
+
+/// how to resolve conflicts when merging a vertex into another one
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MergeStrategy {
+    /// name & properties of the kept vertex win
+    PreferKeep,
+    /// name & properties of the removed vertex win
+    PreferRemove,
+    /// abort if a property has different values in both vertexes,
+    /// name of the kept vertex is used
+    Fail,
+}
+
+impl FromStr for MergeStrategy {
+    type Err = Pyo3MongoError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "prefer_keep" => Ok(MergeStrategy::PreferKeep),
+            "prefer_remove" => Ok(MergeStrategy::PreferRemove),
+            "fail" => Ok(MergeStrategy::Fail),
+            _ => Err(Pyo3MongoError::Common("unknown merge strategy")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_model {
+    use super::*;
+
+    #[test]
+    fn test_neighborhood_cap() {
+        let options = NeighborhoodOptions {
+            per_hop_limit: Some(3),
+            total_limit: Some(4),
+            ..Default::default()
+        };
+        assert_eq!(options.cap(0), Some(3));
+        assert_eq!(options.cap(3), Some(1));
+        assert_eq!(options.cap(5), Some(0));
+
+        let options = NeighborhoodOptions {
+            per_hop_limit: None,
+            total_limit: None,
+            ..Default::default()
+        };
+        assert_eq!(options.cap(100), None);
+    }
+
+    #[test]
+    fn test_hyperedge_vertexes() {
+        let (buyer, seller) = (ObjectId::new(), ObjectId::new());
+        let hyperedge = HyperEdge::from(HyperEdgeDto::new(
+            vec![(buyer, "buyer"), (seller, "seller"), (buyer, "broker")],
+            Some("transaction"),
+        ));
+        assert_eq!(hyperedge.members[2].role, "broker");
+        assert_eq!(hyperedge.vertexes(), vec![buyer, seller]);
+    }
+
+    #[test]
+    fn test_edge_validity() {
+        let day = |d: i64| DateTime::from_millis(d * 86_400_000);
+        let edge = Edge {
+            id: None,
+            source: ObjectId::new(),
+            target: ObjectId::new(),
+            weight: None,
+            label: None,
+            valid_from: Some(day(1)),
+            valid_to: Some(day(2)),
+        };
+        assert!(!edge.is_valid_at(day(0)));
+        assert!(edge.is_valid_at(day(1)));
+        assert!(!edge.is_valid_at(day(2)));
+
+        assert!(check_validity(Some(day(2)), Some(day(1))).is_err());
+        assert!(check_validity(None, Some(day(1))).is_ok());
+        assert!(check_depth(Some(-1)).is_err());
+    }
+
+    // `$jsonSchema` name of a BSON type
+    fn bson_type(value: &Bson) -> &'static str {
+        match value {
+            Bson::ObjectId(_) => "objectId",
+            Bson::String(_) => "string",
+            Bson::Document(_) => "object",
+            Bson::Array(_) => "array",
+            Bson::Double(_) => "double",
+            Bson::Int32(_) => "int",
+            Bson::Int64(_) => "long",
+            Bson::DateTime(_) => "date",
+            Bson::Boolean(_) => "bool",
+            Bson::Null => "null",
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    // every field of `doc` is described by `schema`, with its type, and every
+    // field of `schema` is in `doc`: a model has to be populated in full
+    fn assert_matches(schema: &Document, doc: &Document) {
+        let properties = schema.get_document("properties").unwrap();
+        let keys = properties.keys().collect::<Vec<_>>();
+        assert_eq!(keys, doc.keys().collect::<Vec<_>>());
+
+        for (key, value) in doc {
+            let field = properties.get_document(key).unwrap();
+            let allowed = match field.get("bsonType").unwrap() {
+                Bson::Array(types) => types.iter().map(|t| t.as_str().unwrap()).collect(),
+                t => vec![t.as_str().unwrap()],
+            };
+            assert!(allowed.contains(&bson_type(value)), "{}: {}", key, value);
+
+            if let (Bson::Array(items), Ok(item)) = (value, field.get_document("items")) {
+                for i in items {
+                    assert_matches(item, i.as_document().unwrap());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_json_schema() {
+        let vertex = Vertex {
+            id: Some(ObjectId::new()),
+            name: "node-1".to_string(),
+            properties: Some(doc! {"age": 3}),
+        };
+        assert_matches(&Vertex::json_schema(), &Document::from(&vertex));
+
+        let edge = Edge {
+            id: Some(ObjectId::new()),
+            source: ObjectId::new(),
+            target: ObjectId::new(),
+            weight: Some(1.5),
+            label: Some("knows".to_string()),
+            valid_from: Some(DateTime::from_millis(0)),
+            valid_to: Some(DateTime::from_millis(1)),
+        };
+        assert_matches(&Edge::json_schema(), &Document::from(&edge));
+        let schema = Edge::json_schema();
+        assert_eq!(
+            schema.get_array("required").unwrap(),
+            &vec![Bson::from("source"), Bson::from("target")]
+        );
+        assert_eq!(
+            schema
+                .get_document("properties")
+                .unwrap()
+                .get_document("weight")
+                .unwrap(),
+            &doc! {"bsonType": ["double", "int", "long", "null"]}
+        );
+
+        let (buyer, seller) = (ObjectId::new(), ObjectId::new());
+        let hyperedge = HyperEdge {
+            id: Some(ObjectId::new()),
+            properties: Some(doc! {"amount": 10}),
+            ..HyperEdge::from(HyperEdgeDto::new(
+                vec![(buyer, "buyer"), (seller, "seller")],
+                Some("transaction"),
+            ))
+        };
+        assert_matches(
+            &HyperEdge::json_schema(),
+            &bson::to_document(&hyperedge).unwrap(),
+        );
+    }
+}
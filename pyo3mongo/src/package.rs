@@ -1,13 +1,41 @@
 //! Pyo3 Async
 
+// `#[pymethods]` of pyo3 0.16 expands into non-local impl blocks
+#![allow(non_local_definitions)]
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
 use std::str::FromStr;
+use std::time::Duration;
 
 use bson::oid::ObjectId;
-use pyo3::exceptions::{PyBaseException, PyValueError};
+use bson::{doc, Bson, DateTime, Document};
+use mongodb::options::ValidationAction;
+use pyo3::basic::CompareOp;
+use pyo3::exceptions::{PyBaseException, PyTypeError, PyValueError};
 use pyo3::prelude::*;
+use pyo3::types::{
+    PyBool, PyBytes, PyDateTime, PyDict, PyFloat, PyList, PyLong, PyString, PyTuple,
+};
+use pyo3::PyClass;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
 use tokio::runtime::Runtime;
 
-use crate::{Edge, EdgeDto, GraphService, Pyo3MongoError, Vertex, VertexDto};
+use crate::cache::{CacheMetrics, CacheOptions, CacheStats};
+use crate::centrality::PageRankOptions;
+use crate::diff::{DiffKey, EdgeChange, GraphDiff, VertexChange};
+use crate::query::BindingValue;
+use crate::retry::RetryPolicy;
+use crate::sampling::{Sample, SnowballOptions, WalkOptions, Walks};
+use crate::telemetry;
+use crate::weights::{AdjacencyMatrix, LabelWeights, VertexWeight};
+use crate::{
+    AccessPolicy, Edge, EdgeDto, GraphScope, GraphService, GraphStats, HyperEdge, HyperEdgeDto,
+    IndexStatus, IntegrityReport, MergeStrategy, MongoConfig, Neighborhood, NeighborhoodOptions,
+    Pyo3MongoError, Pyo3MongoResult, RepairAction, ValidationReport, Vertex, VertexDto,
+};
 
 // turn Pyo3MongoError into PyResult
 impl From<Pyo3MongoError> for PyErr {
@@ -16,15 +44,266 @@ impl From<Pyo3MongoError> for PyErr {
     }
 }
 
+// turn a bson value into a Python object, ObjectId & datetime are turned into strings
+fn bson_to_py(py: Python, value: &Bson) -> PyObject {
+    match value {
+        Bson::Double(v) => v.into_py(py),
+        Bson::String(v) => v.into_py(py),
+        Bson::Boolean(v) => v.into_py(py),
+        Bson::Int32(v) => v.into_py(py),
+        Bson::Int64(v) => v.into_py(py),
+        Bson::ObjectId(v) => v.to_hex().into_py(py),
+        Bson::Array(v) => PyList::new(py, v.iter().map(|i| bson_to_py(py, i))).into_py(py),
+        Bson::Document(v) => {
+            let dict = PyDict::new(py);
+            for (k, i) in v {
+                // inserting a string key never fails
+                dict.set_item(k, bson_to_py(py, i)).unwrap();
+            }
+            dict.into_py(py)
+        }
+        Bson::DateTime(v) => datetime_to_py(py, *v),
+        Bson::Null | Bson::Undefined => py.None(),
+        other => other.to_string().into_py(py),
+    }
+}
+
+// a timezone-aware `datetime` in UTC, a string if out of Python's range
+fn datetime_to_py(py: Python, value: DateTime) -> PyObject {
+    let to_py = || -> PyResult<PyObject> {
+        let datetime = py.import("datetime")?;
+        let utc = datetime.getattr("timezone")?.getattr("utc")?;
+        let seconds = value.timestamp_millis() as f64 / 1000.0;
+        let res = datetime
+            .getattr("datetime")?
+            .call_method1("fromtimestamp", (seconds, utc))?;
+        Ok(res.into_py(py))
+    };
+    to_py().unwrap_or_else(|_| value.to_string().into_py(py))
+}
+
+// a naive `datetime` is taken as local time, as `datetime.timestamp` does
+fn py_to_datetime(value: &PyAny) -> PyResult<DateTime> {
+    match py_to_bson(value)? {
+        Bson::DateTime(v) => Ok(v),
+        _ => Err(PyTypeError::new_err("a datetime is expected")),
+    }
+}
+
+// turn a Python object into a bson value, the reverse of `bson_to_py`
+fn py_to_bson(value: &PyAny) -> PyResult<Bson> {
+    // `bool` is a subclass of `int`, so it goes first
+    if value.is_none() {
+        Ok(Bson::Null)
+    } else if let Ok(v) = value.downcast::<PyBool>() {
+        Ok(Bson::Boolean(v.is_true()))
+    } else if let Ok(v) = value.downcast::<PyLong>() {
+        Ok(Bson::Int64(v.extract()?))
+    } else if let Ok(v) = value.downcast::<PyFloat>() {
+        Ok(Bson::Double(v.value()))
+    } else if let Ok(v) = value.downcast::<PyString>() {
+        Ok(Bson::String(v.to_str()?.to_owned()))
+    } else if let Ok(v) = value.downcast::<PyDict>() {
+        let mut doc = Document::new();
+        for (k, i) in v {
+            doc.insert(k.str()?.to_str()?, py_to_bson(i)?);
+        }
+        Ok(Bson::Document(doc))
+    } else if let Ok(v) = value.downcast::<PyList>() {
+        Ok(Bson::Array(
+            v.iter().map(py_to_bson).collect::<PyResult<_>>()?,
+        ))
+    } else if let Ok(v) = value.downcast::<PyTuple>() {
+        Ok(Bson::Array(
+            v.iter().map(py_to_bson).collect::<PyResult<_>>()?,
+        ))
+    } else if let Ok(v) = value.downcast::<PyDateTime>() {
+        let seconds: f64 = v.call_method0("timestamp")?.extract()?;
+        Ok(Bson::DateTime(DateTime::from_millis(
+            (seconds * 1000.0).round() as i64,
+        )))
+    } else if let Ok(v) = value.extract::<i64>() {
+        // numbers which are not builtin, e.g. numpy's
+        Ok(Bson::Int64(v))
+    } else if let Ok(v) = value.extract::<f64>() {
+        Ok(Bson::Double(v))
+    } else {
+        Err(PyTypeError::new_err(format!(
+            "unsupported type: {}",
+            value.get_type().name()?
+        )))
+    }
+}
+
+// Python data model helpers, shared by `Vertex`, `Edge`, `EdgeInput` & `GraphOutput`.
+// Conversions go through serde derives: `_id` is renamed as `id`, the name of
+// the Python attribute, and ObjectIds are hex strings.
+
+fn to_dict<T: Serialize>(py: Python, value: &T) -> PyResult<PyObject> {
+    let mut doc = bson::to_document(value).map_err(Pyo3MongoError::from)?;
+    if let Some(id) = doc.remove("_id") {
+        // keep `id` as the first field
+        let mut with_id = doc! {"id": id};
+        with_id.extend(doc);
+        doc = with_id;
+    }
+    Ok(bson_to_py(py, &Bson::Document(doc)))
+}
+
+fn from_dict<T: DeserializeOwned>(
+    dict: &PyDict,
+    oid_fields: &[&str],
+    datetime_fields: &[&str],
+) -> PyResult<T> {
+    let mut doc = match py_to_bson(dict)? {
+        Bson::Document(d) => d,
+        _ => unreachable!(),
+    };
+    if let Some(id) = doc.remove("id") {
+        doc.insert("_id", id);
+    }
+    for field in oid_fields {
+        if let Some(Bson::String(hex)) = doc.get(field) {
+            let oid = ObjectId::from_str(hex).map_err(|e| PyValueError::new_err(e.to_string()))?;
+            doc.insert(*field, oid);
+        }
+    }
+    // from JSON, datetimes are RFC 3339 strings
+    for field in datetime_fields {
+        if let Some(Bson::String(s)) = doc.get(field) {
+            let dt =
+                DateTime::parse_rfc3339_str(s).map_err(|e| PyValueError::new_err(e.to_string()))?;
+            doc.insert(*field, dt);
+        }
+    }
+    // a missing optional id is `null` in a dict, but absent in a document
+    if doc.get("_id") == Some(&Bson::Null) {
+        doc.remove("_id");
+    }
+    bson::from_document(doc).map_err(|e| PyValueError::new_err(e.to_string()))
+}
+
+fn to_json(py: Python, dict: PyObject) -> PyResult<String> {
+    // datetimes are the only values not serializable by default
+    let kwargs = PyDict::new(py);
+    let isoformat = py
+        .import("datetime")?
+        .getattr("datetime")?
+        .getattr("isoformat")?;
+    kwargs.set_item("default", isoformat)?;
+    py.import("json")?
+        .call_method("dumps", (dict,), Some(kwargs))?
+        .extract()
+}
+
+fn from_json<'p>(py: Python<'p>, s: &str) -> PyResult<&'p PyDict> {
+    Ok(py.import("json")?.call_method1("loads", (s,))?.downcast()?)
+}
+
+// pickle state, raw bson bytes
+fn to_state<T: Serialize>(py: Python, value: &T) -> PyResult<PyObject> {
+    let bytes = bson::to_vec(value).map_err(Pyo3MongoError::from)?;
+    Ok(PyBytes::new(py, &bytes).into_py(py))
+}
+
+fn from_state<T: DeserializeOwned>(state: &PyBytes) -> PyResult<T> {
+    Ok(bson::from_slice(state.as_bytes()).map_err(Pyo3MongoError::from)?)
+}
+
+fn hash_of(value: impl Hash) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
+// bits of a weight to hash: -0.0 equals 0.0, so they hash the same, as do NaNs
+fn weight_bits(weight: Option<f64>) -> Option<u64> {
+    weight.map(|w| {
+        if w == 0.0 {
+            0f64.to_bits()
+        } else if w.is_nan() {
+            f64::NAN.to_bits()
+        } else {
+            w.to_bits()
+        }
+    })
+}
+
+fn richcmp<T: PyClass + PartialEq>(py: Python, this: &T, other: &PyAny, op: CompareOp) -> PyObject {
+    match (other.extract::<PyRef<T>>(), op) {
+        (Ok(o), CompareOp::Eq) => (this == &*o).into_py(py),
+        (Ok(o), CompareOp::Ne) => (this != &*o).into_py(py),
+        _ => py.NotImplemented(),
+    }
+}
+
+// `Name(key=value, ...)`, values are shown by their Python `repr`
+fn repr(py: Python, name: &str, dict: PyObject) -> PyResult<String> {
+    let dict: &PyDict = dict.as_ref(py).downcast()?;
+    let fields = dict
+        .iter()
+        .map(|(k, v)| Ok(format!("{}={}", k.str()?, v.repr()?)))
+        .collect::<PyResult<Vec<_>>>()?;
+    Ok(format!("{}({})", name, fields.join(", ")))
+}
+
+// items of a batch which could be parsed, and the parse errors in place (`None`
+// for the items kept)
+fn split_batch<A>(parsed: Vec<Pyo3MongoResult<A>>) -> (Vec<A>, Vec<Option<Pyo3MongoError>>) {
+    let mut items = Vec::with_capacity(parsed.len());
+    let slots = parsed
+        .into_iter()
+        .map(|p| p.map(|a| items.push(a)).err())
+        .collect();
+    (items, slots)
+}
+
+// put the results of the items kept by `split_batch` back among the parse errors
+fn merge_batch<T>(
+    slots: Vec<Option<Pyo3MongoError>>,
+    results: Vec<Pyo3MongoResult<T>>,
+) -> Vec<Pyo3MongoResult<T>> {
+    let mut results = results.into_iter();
+    slots
+        .into_iter()
+        .map(|slot| match slot {
+            Some(e) => Err(e),
+            None => results.next().unwrap(),
+        })
+        .collect()
+}
+
+// per-item results of a batch, a failed item is an exception instance (not
+// raised), like `asyncio.gather(..., return_exceptions=True)`
+fn batch_to_py<T: IntoPy<PyObject>>(py: Python, res: Vec<Pyo3MongoResult<T>>) -> Vec<PyObject> {
+    res.into_iter()
+        .map(|r| match r {
+            Ok(v) => v.into_py(py),
+            Err(e) => PyErr::from(e).into_py(py),
+        })
+        .collect()
+}
+
 // getter & setter for Vertex
 #[pymethods]
 impl Vertex {
+    #[new]
+    fn py_new(name: String, properties: Option<&PyDict>) -> PyResult<Self> {
+        let properties = match properties {
+            Some(p) => Some(bson::from_bson(py_to_bson(p)?).map_err(Pyo3MongoError::from)?),
+            None => None,
+        };
+        Ok(Vertex {
+            id: None,
+            name,
+            properties,
+        })
+    }
+
+    /// `None` if the vertex has not been stored yet
     #[getter]
-    pub fn get_id(&self) -> PyResult<String> {
-        // since `Vertex` is always returned by `create_vertex`,
-        // which means `id` is always given by MongoDB,
-        // it is always safe to unwrap the `id`
-        Ok(self.id.unwrap().to_string())
+    pub fn get_id(&self) -> PyResult<Option<String>> {
+        Ok(self.id.map(|id| id.to_hex()))
     }
 
     #[getter]
@@ -37,17 +316,97 @@ impl Vertex {
         self.name = name.to_owned();
         Ok(())
     }
+
+    #[getter]
+    pub fn get_properties(&self, py: Python) -> PyResult<PyObject> {
+        Ok(match &self.properties {
+            Some(p) => bson_to_py(py, &Bson::Document(p.clone())),
+            None => py.None(),
+        })
+    }
+
+    #[setter]
+    pub fn set_properties(&mut self, value: Option<&PyDict>) -> PyResult<()> {
+        self.properties = match value {
+            Some(p) => Some(bson::from_bson(py_to_bson(p)?).map_err(Pyo3MongoError::from)?),
+            None => None,
+        };
+        Ok(())
+    }
+
+    fn __repr__(&self, py: Python) -> PyResult<String> {
+        repr(py, "Vertex", self.to_dict(py)?)
+    }
+
+    fn __richcmp__(&self, other: &PyAny, op: CompareOp, py: Python) -> PyObject {
+        richcmp(py, self, other, op)
+    }
+
+    // properties are left out, equal vertexes still have equal hashes
+    fn __hash__(&self) -> u64 {
+        hash_of((self.id, &self.name))
+    }
+
+    fn __getnewargs__(&self) -> (String,) {
+        (self.name.clone(),)
+    }
+
+    fn __getstate__(&self, py: Python) -> PyResult<PyObject> {
+        to_state(py, self)
+    }
+
+    fn __setstate__(&mut self, state: &PyBytes) -> PyResult<()> {
+        *self = from_state(state)?;
+        Ok(())
+    }
+
+    pub fn to_dict(&self, py: Python) -> PyResult<PyObject> {
+        to_dict(py, self)
+    }
+
+    #[staticmethod]
+    pub fn from_dict(dict: &PyDict) -> PyResult<Self> {
+        from_dict(dict, &["_id"], &[])
+    }
+
+    pub fn to_json(&self, py: Python) -> PyResult<String> {
+        to_json(py, self.to_dict(py)?)
+    }
+
+    #[staticmethod]
+    pub fn from_json(py: Python, s: &str) -> PyResult<Self> {
+        Self::from_dict(from_json(py, s)?)
+    }
 }
 
 // getter & setter for Edge
 #[pymethods]
 impl Edge {
+    #[new]
+    fn py_new(
+        source: &str,
+        target: &str,
+        weight: Option<f64>,
+        label: Option<String>,
+        valid_from: Option<&PyAny>,
+        valid_to: Option<&PyAny>,
+    ) -> PyResult<Self> {
+        let oid = |v: &str| ObjectId::from_str(v).map_err(|e| PyValueError::new_err(e.to_string()));
+        Ok(Edge {
+            id: None,
+            source: oid(source)?,
+            target: oid(target)?,
+            weight,
+            label,
+            valid_from: valid_from.map(py_to_datetime).transpose()?,
+            valid_to: valid_to.map(py_to_datetime).transpose()?,
+        })
+    }
+
+    /// `None` if the edge has not been stored yet
     #[getter]
-    pub fn get_id(&self) -> PyResult<String> {
-        // since `Edge` is always returned by `create_edge`,
-        // which means `id` is always given by MongoDB,
-        // it is always safe to unwrap the `id`
-        Ok(self.id.unwrap().to_hex())
+    pub fn get_id(&self) -> PyResult<Option<String>> {
+        Ok(self.id.map(|id| id.to_hex()))
     }
 
     #[getter]
@@ -105,16 +464,287 @@ impl Edge {
         self.label = Some(value.to_string());
         Ok(())
     }
+
+    #[getter]
+    pub fn get_valid_from(&self, py: Python) -> Option<PyObject> {
+        self.valid_from.map(|v| datetime_to_py(py, v))
+    }
+
+    #[setter]
+    pub fn set_valid_from(&mut self, value: Option<&PyAny>) -> PyResult<()> {
+        self.valid_from = value.map(py_to_datetime).transpose()?;
+        Ok(())
+    }
+
+    #[getter]
+    pub fn get_valid_to(&self, py: Python) -> Option<PyObject> {
+        self.valid_to.map(|v| datetime_to_py(py, v))
+    }
+
+    #[setter]
+    pub fn set_valid_to(&mut self, value: Option<&PyAny>) -> PyResult<()> {
+        self.valid_to = value.map(py_to_datetime).transpose()?;
+        Ok(())
+    }
+
+    fn __repr__(&self, py: Python) -> PyResult<String> {
+        repr(py, "Edge", self.to_dict(py)?)
+    }
+
+    fn __richcmp__(&self, other: &PyAny, op: CompareOp, py: Python) -> PyObject {
+        richcmp(py, self, other, op)
+    }
+
+    fn __hash__(&self) -> u64 {
+        let weight = weight_bits(self.weight);
+        hash_of((
+            self.id,
+            self.source,
+            self.target,
+            weight,
+            &self.label,
+            self.valid_from,
+            self.valid_to,
+        ))
+    }
+
+    fn __getnewargs__(&self) -> (String, String) {
+        (self.source.to_hex(), self.target.to_hex())
+    }
+
+    fn __getstate__(&self, py: Python) -> PyResult<PyObject> {
+        to_state(py, self)
+    }
+
+    fn __setstate__(&mut self, state: &PyBytes) -> PyResult<()> {
+        *self = from_state(state)?;
+        Ok(())
+    }
+
+    pub fn to_dict(&self, py: Python) -> PyResult<PyObject> {
+        to_dict(py, self)
+    }
+
+    #[staticmethod]
+    pub fn from_dict(dict: &PyDict) -> PyResult<Self> {
+        from_dict(
+            dict,
+            &["_id", "source", "target"],
+            &["valid_from", "valid_to"],
+        )
+    }
+
+    pub fn to_json(&self, py: Python) -> PyResult<String> {
+        to_json(py, self.to_dict(py)?)
+    }
+
+    #[staticmethod]
+    pub fn from_json(py: Python, s: &str) -> PyResult<Self> {
+        Self::from_dict(from_json(py, s)?)
+    }
+}
+
+// getters for IntegrityReport, ids are turned into strings
+#[pymethods]
+impl IntegrityReport {
+    #[getter]
+    pub fn get_dangling_edges(&self, py: Python) -> Vec<PyObject> {
+        self.dangling_edges
+            .iter()
+            .map(|i| bson_to_py(py, i))
+            .collect()
+    }
+
+    #[getter]
+    pub fn get_duplicate_edges(&self, py: Python) -> Vec<Vec<PyObject>> {
+        self.duplicate_edges
+            .iter()
+            .map(|ids| ids.iter().map(|i| bson_to_py(py, i)).collect())
+            .collect()
+    }
+
+    #[getter]
+    pub fn get_invalid_edges(&self, py: Python) -> Vec<PyObject> {
+        self.invalid_edges
+            .iter()
+            .map(|i| bson_to_py(py, i))
+            .collect()
+    }
+
+    #[getter]
+    pub fn get_invalid_vertexes(&self, py: Python) -> Vec<PyObject> {
+        self.invalid_vertexes
+            .iter()
+            .map(|i| bson_to_py(py, i))
+            .collect()
+    }
+
+    #[getter]
+    pub fn get_dangling_hyperedges(&self, py: Python) -> Vec<PyObject> {
+        self.dangling_hyperedges
+            .iter()
+            .map(|i| bson_to_py(py, i))
+            .collect()
+    }
+
+    #[getter(is_clean)]
+    pub fn py_is_clean(&self) -> bool {
+        self.is_clean()
+    }
+}
+
+#[pymethods]
+impl GraphDiff {
+    #[getter(is_empty)]
+    pub fn py_is_empty(&self) -> bool {
+        self.is_empty()
+    }
+}
+
+// getters for HyperEdge, ids are turned into strings
+#[pymethods]
+impl HyperEdge {
+    /// `None` if the hyperedge has not been stored yet
+    #[getter]
+    pub fn get_id(&self) -> Option<String> {
+        self.id.map(|id| id.to_hex())
+    }
+
+    #[getter]
+    pub fn get_label(&self) -> Option<String> {
+        self.label.clone()
+    }
+
+    /// `(vertex, role)` pairs
+    #[getter]
+    pub fn get_members(&self) -> Vec<(String, String)> {
+        self.members
+            .iter()
+            .map(|m| (m.vertex.to_hex(), m.role.clone()))
+            .collect()
+    }
+
+    #[getter]
+    pub fn get_properties(&self, py: Python) -> PyObject {
+        match &self.properties {
+            Some(p) => bson_to_py(py, &Bson::Document(p.clone())),
+            None => py.None(),
+        }
+    }
+
+    fn __repr__(&self, py: Python) -> PyResult<String> {
+        repr(py, "HyperEdge", self.to_dict(py)?)
+    }
+
+    pub fn to_dict(&self, py: Python) -> PyResult<PyObject> {
+        to_dict(py, self)
+    }
+}
+
+#[pymethods]
+impl VertexWeight {
+    #[getter]
+    pub fn get_vertex(&self) -> String {
+        self.vertex.to_hex()
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "VertexWeight(vertex={}, total={}, average={}, count={})",
+            self.vertex, self.total, self.average, self.count
+        )
+    }
+}
+
+#[pymethods]
+impl AdjacencyMatrix {
+    /// rows & columns, in order
+    #[getter]
+    pub fn get_vertexes(&self) -> Vec<String> {
+        self.vertexes.iter().map(|v| v.to_hex()).collect()
+    }
+
+    /// total weight of edges from `source` to `target`, `None` if either is
+    /// out of the matrix
+    #[pyo3(name = "get")]
+    pub fn py_get(&self, source: &str, target: &str) -> PyResult<Option<f64>> {
+        let source = ObjectId::from_str(source).map_err(Pyo3MongoError::from)?;
+        let target = ObjectId::from_str(target).map_err(Pyo3MongoError::from)?;
+        Ok(self.get(source, target))
+    }
+}
+
+#[pymethods]
+impl Walks {
+    /// vertexes walks refer to by position
+    #[getter]
+    pub fn get_vertexes(&self) -> Vec<String> {
+        self.vertexes.iter().map(|v| v.to_hex()).collect()
+    }
+
+    /// walks of vertex ids instead of positions
+    pub fn to_ids(&self) -> Vec<Vec<String>> {
+        let ids = self.get_vertexes();
+        self.walks
+            .iter()
+            .map(|w| w.iter().map(|i| ids[*i as usize].clone()).collect())
+            .collect()
+    }
+
+    fn __len__(&self) -> usize {
+        self.walks.len()
+    }
+}
+
+#[pymethods]
+impl Sample {
+    /// vertexes edges refer to by position, the seed vertex first
+    #[getter]
+    pub fn get_vertexes(&self) -> Vec<String> {
+        self.vertexes.iter().map(|v| v.to_hex()).collect()
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "Sample(vertexes={}, edges={})",
+            self.vertexes.len(),
+            self.edges.len()
+        )
+    }
+}
+
+// getters for ValidationReport, ids are turned into strings
+#[pymethods]
+impl ValidationReport {
+    #[getter]
+    pub fn get_vertexes(&self, py: Python) -> Vec<PyObject> {
+        self.vertexes.iter().map(|i| bson_to_py(py, i)).collect()
+    }
+
+    #[getter]
+    pub fn get_edges(&self, py: Python) -> Vec<PyObject> {
+        self.edges.iter().map(|i| bson_to_py(py, i)).collect()
+    }
+
+    #[getter]
+    pub fn get_hyperedges(&self, py: Python) -> Vec<PyObject> {
+        self.hyperedges.iter().map(|i| bson_to_py(py, i)).collect()
+    }
+
+    #[getter(is_valid)]
+    pub fn py_is_valid(&self) -> bool {
+        self.is_valid()
+    }
 }
 
-#[pyclass]
+#[pyclass(module = "p3m")]
 pub struct PyGraph {
     service: GraphService,
     runtime: Runtime,
 }
 
-#[pyclass]
-#[derive(FromPyObject)]
+#[pyclass(module = "p3m")]
+#[derive(FromPyObject, Serialize, Deserialize, PartialEq, Debug)]
 pub struct EdgeInput {
     #[pyo3(get, set)]
     pub source: String,
@@ -124,18 +754,103 @@ pub struct EdgeInput {
     pub weight: Option<f64>,
     #[pyo3(get, set)]
     pub label: Option<String>,
+    /// `source` & `target` are vertex names rather than ids
+    #[pyo3(get, set)]
+    #[serde(default)]
+    pub by_name: bool,
 }
 
 #[pymethods]
 impl EdgeInput {
     #[new]
-    fn new(source: String, target: String, weight: Option<f64>, label: Option<String>) -> Self {
+    #[args(weight = "None", label = "None", by_name = "false")]
+    fn new(
+        source: String,
+        target: String,
+        weight: Option<f64>,
+        label: Option<String>,
+        by_name: bool,
+    ) -> Self {
         EdgeInput {
             source,
             target,
             weight,
             label,
+            by_name,
+        }
+    }
+
+    fn __repr__(&self, py: Python) -> PyResult<String> {
+        repr(py, "EdgeInput", self.to_dict(py)?)
+    }
+
+    fn __richcmp__(&self, other: &PyAny, op: CompareOp, py: Python) -> PyObject {
+        richcmp(py, self, other, op)
+    }
+
+    fn __hash__(&self) -> u64 {
+        let weight = weight_bits(self.weight);
+        hash_of((
+            &self.source,
+            &self.target,
+            weight,
+            &self.label,
+            self.by_name,
+        ))
+    }
+
+    fn __getnewargs__(&self) -> (String, String) {
+        (self.source.clone(), self.target.clone())
+    }
+
+    fn __getstate__(&self, py: Python) -> PyResult<PyObject> {
+        to_state(py, self)
+    }
+
+    fn __setstate__(&mut self, state: &PyBytes) -> PyResult<()> {
+        *self = from_state(state)?;
+        Ok(())
+    }
+
+    pub fn to_dict(&self, py: Python) -> PyResult<PyObject> {
+        to_dict(py, self)
+    }
+
+    #[staticmethod]
+    pub fn from_dict(dict: &PyDict) -> PyResult<Self> {
+        from_dict(dict, &[], &[])
+    }
+
+    pub fn to_json(&self, py: Python) -> PyResult<String> {
+        to_json(py, self.to_dict(py)?)
+    }
+
+    #[staticmethod]
+    pub fn from_json(py: Python, s: &str) -> PyResult<Self> {
+        Self::from_dict(from_json(py, s)?)
+    }
+}
+
+impl EdgeInput {
+    /// names of the endpoints, if they are given by name
+    fn names(&self) -> Option<[&str; 2]> {
+        self.by_name
+            .then_some([self.source.as_str(), self.target.as_str()])
+    }
+
+    /// endpoints given by name are looked up in `ids`
+    fn resolve<'a>(&'a self, ids: &HashMap<String, ObjectId>) -> Pyo3MongoResult<EdgeDto<'a>> {
+        if !self.by_name {
+            return EdgeDto::try_from(self);
         }
+
+        let id = |name: &String| ids.get(name).copied().ok_or(Pyo3MongoError::VertexNotFound);
+        Ok(EdgeDto::new(
+            id(&self.source)?,
+            id(&self.target)?,
+            self.weight,
+            self.label.as_deref(),
+        ))
     }
 }
 
@@ -143,17 +858,52 @@ impl<'a> TryFrom<&'a EdgeInput> for EdgeDto<'a> {
     type Error = Pyo3MongoError;
 
     fn try_from(value: &'a EdgeInput) -> Result<Self, Self::Error> {
+        if value.by_name {
+            return Err(Pyo3MongoError::Common("endpoints are given by name"));
+        }
+
         let v = EdgeDto {
             source: ObjectId::from_str(&value.source)?,
             target: ObjectId::from_str(&value.target)?,
             weight: value.weight,
             label: value.label.as_deref(),
+            valid_from: None,
+            valid_to: None,
         };
         Ok(v)
     }
 }
 
-#[pyclass]
+// `{id, name, **properties}`, a flat record of a vertex
+fn vertex_record<'p>(py: Python<'p>, v: &Vertex) -> PyResult<&'p PyDict> {
+    let id =
+        v.id.ok_or_else(|| PyValueError::new_err("vertex has not been stored yet"))?;
+    let record = PyDict::new(py);
+    record.set_item("id", id.to_hex())?;
+    record.set_item("name", &v.name)?;
+    for (k, i) in v.properties.iter().flatten() {
+        if k != "id" && k != "name" {
+            record.set_item(k, bson_to_py(py, i))?;
+        }
+    }
+    Ok(record)
+}
+
+// `{id, source, target, weight, label}`, a flat record of an edge
+fn edge_record<'p>(py: Python<'p>, e: &Edge) -> PyResult<&'p PyDict> {
+    let record = PyDict::new(py);
+    record.set_item("id", e.id.map(|id| id.to_hex()))?;
+    record.set_item("source", e.source.to_hex())?;
+    record.set_item("target", e.target.to_hex())?;
+    record.set_item("weight", e.weight)?;
+    record.set_item("label", &e.label)?;
+    record.set_item("valid_from", e.valid_from.map(|v| datetime_to_py(py, v)))?;
+    record.set_item("valid_to", e.valid_to.map(|v| datetime_to_py(py, v)))?;
+    Ok(record)
+}
+
+#[pyclass(module = "p3m")]
+#[derive(Serialize, Deserialize, PartialEq, Clone, Debug)]
 pub struct GraphOutput {
     #[pyo3(get)]
     pub vertexes: Vec<Vertex>,
@@ -162,12 +912,266 @@ pub struct GraphOutput {
 }
 
 #[pymethods]
-impl PyGraph {
+impl GraphOutput {
     #[new]
-    fn new_graph(uri: String, database: String, category: String) -> PyResult<PyGraph> {
-        let runtime = tokio::runtime::Runtime::new().unwrap();
-        let service =
-            runtime.block_on(async move { GraphService::new(&uri, &database, &category).await })?;
+    fn new(vertexes: Option<Vec<Vertex>>, edges: Option<Vec<Edge>>) -> Self {
+        GraphOutput {
+            vertexes: vertexes.unwrap_or_default(),
+            edges: edges.unwrap_or_default(),
+        }
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "GraphOutput(vertexes=<{} vertexes>, edges=<{} edges>)",
+            self.vertexes.len(),
+            self.edges.len()
+        )
+    }
+
+    fn __richcmp__(&self, other: &PyAny, op: CompareOp, py: Python) -> PyObject {
+        richcmp(py, self, other, op)
+    }
+
+    fn __hash__(&self) -> u64 {
+        let vertexes = self.vertexes.iter().map(|v| v.id).collect::<Vec<_>>();
+        let edges = self.edges.iter().map(|e| e.id).collect::<Vec<_>>();
+        hash_of((vertexes, edges))
+    }
+
+    fn __getstate__(&self, py: Python) -> PyResult<PyObject> {
+        to_state(py, self)
+    }
+
+    fn __setstate__(&mut self, state: &PyBytes) -> PyResult<()> {
+        *self = from_state(state)?;
+        Ok(())
+    }
+
+    pub fn to_dict(&self, py: Python) -> PyResult<PyObject> {
+        let dict = PyDict::new(py);
+        let vertexes = self
+            .vertexes
+            .iter()
+            .map(|v| v.to_dict(py))
+            .collect::<PyResult<Vec<_>>>()?;
+        let edges = self
+            .edges
+            .iter()
+            .map(|e| e.to_dict(py))
+            .collect::<PyResult<Vec<_>>>()?;
+        dict.set_item("vertexes", vertexes)?;
+        dict.set_item("edges", edges)?;
+        Ok(dict.into_py(py))
+    }
+
+    #[staticmethod]
+    pub fn from_dict(dict: &PyDict) -> PyResult<Self> {
+        let items = |key: &str| -> PyResult<Vec<&PyDict>> {
+            match dict.get_item(key) {
+                Some(v) => v.extract(),
+                None => Ok(vec![]),
+            }
+        };
+        Ok(GraphOutput {
+            vertexes: items("vertexes")?
+                .into_iter()
+                .map(Vertex::from_dict)
+                .collect::<PyResult<_>>()?,
+            edges: items("edges")?
+                .into_iter()
+                .map(Edge::from_dict)
+                .collect::<PyResult<_>>()?,
+        })
+    }
+
+    pub fn to_json(&self, py: Python) -> PyResult<String> {
+        to_json(py, self.to_dict(py)?)
+    }
+
+    #[staticmethod]
+    pub fn from_json(py: Python, s: &str) -> PyResult<Self> {
+        Self::from_dict(from_json(py, s)?)
+    }
+
+    /// a `networkx.MultiDiGraph`, nodes are vertex ids with `name` & properties
+    /// as attributes, edges are keyed by their ids with `weight` & `label`
+    pub fn to_networkx(&self, py: Python) -> PyResult<PyObject> {
+        let graph = py.import("networkx")?.getattr("MultiDiGraph")?.call0()?;
+
+        for v in &self.vertexes {
+            let attrs = vertex_record(py, v)?;
+            let id = attrs.get_item("id").unwrap();
+            attrs.del_item("id")?;
+            graph.call_method("add_node", (id,), Some(attrs))?;
+        }
+        for e in &self.edges {
+            let attrs = edge_record(py, e)?;
+            let (source, target) = (e.source.to_hex(), e.target.to_hex());
+            attrs.set_item("key", attrs.get_item("id"))?;
+            for k in ["id", "source", "target"] {
+                attrs.del_item(k)?;
+            }
+            graph.call_method("add_edge", (source, target), Some(attrs))?;
+        }
+
+        Ok(graph.into_py(py))
+    }
+
+    /// `(vertexes, edges)` as two `pandas.DataFrame`s, vertex properties are
+    /// spread into columns
+    pub fn to_pandas(&self, py: Python) -> PyResult<(PyObject, PyObject)> {
+        let frame = py.import("pandas")?.getattr("DataFrame")?;
+
+        let mut vertex_columns = vec!["id".to_owned(), "name".to_owned()];
+        let mut vertexes = Vec::with_capacity(self.vertexes.len());
+        for v in &self.vertexes {
+            let record = vertex_record(py, v)?;
+            for k in record.keys() {
+                let k = k.extract::<String>()?;
+                if !vertex_columns.contains(&k) {
+                    vertex_columns.push(k);
+                }
+            }
+            vertexes.push(record);
+        }
+        let edges = self
+            .edges
+            .iter()
+            .map(|e| edge_record(py, e))
+            .collect::<PyResult<Vec<_>>>()?;
+        let edge_columns = vec![
+            "id",
+            "source",
+            "target",
+            "weight",
+            "label",
+            "valid_from",
+            "valid_to",
+        ];
+
+        let kwargs = PyDict::new(py);
+        kwargs.set_item("columns", vertex_columns)?;
+        let vertexes = frame.call((vertexes,), Some(kwargs))?;
+        kwargs.set_item("columns", edge_columns)?;
+        let edges = frame.call((edges,), Some(kwargs))?;
+
+        Ok((vertexes.into_py(py), edges.into_py(py)))
+    }
+}
+
+impl PyGraph {
+    // `vertex_id` given: traversal from the vertex, otherwise the whole category
+    fn scope<'a>(
+        vertex_id: Option<&str>,
+        label: Option<&'a str>,
+        depth: Option<i32>,
+    ) -> Pyo3MongoResult<GraphScope<'a>> {
+        match vertex_id {
+            Some(id) => Ok(GraphScope::Traversal {
+                vertex_id: ObjectId::from_str(id)?,
+                label,
+                depth,
+                at: None,
+            }),
+            None => Ok(GraphScope::Category),
+        }
+    }
+
+    // members given as `(vertex_id, role)` pairs
+    fn hyperedge_dto<'a>(
+        members: &'a [(String, String)],
+        label: Option<&'a str>,
+        properties: Option<&PyDict>,
+    ) -> PyResult<HyperEdgeDto<'a>> {
+        let members = members
+            .iter()
+            .map(|(id, role)| Ok((ObjectId::from_str(id)?, role.as_str())))
+            .collect::<Pyo3MongoResult<Vec<_>>>()?;
+        let mut dto = HyperEdgeDto::new(members, label);
+        if let Some(p) = properties {
+            let p = bson::from_bson(py_to_bson(p)?).map_err(Pyo3MongoError::from)?;
+            dto = dto.with_properties(p);
+        }
+
+        Ok(dto)
+    }
+
+    // edges to create or update, endpoints given by name are looked up at once
+    fn edge_dtos<'a>(
+        &self,
+        inputs: impl IntoIterator<Item = &'a EdgeInput>,
+    ) -> PyResult<Vec<Pyo3MongoResult<EdgeDto<'a>>>> {
+        let inputs = inputs.into_iter().collect::<Vec<_>>();
+        let names = inputs
+            .iter()
+            .filter_map(|v| v.names())
+            .flatten()
+            .collect::<Vec<_>>();
+
+        let ids = if names.is_empty() {
+            HashMap::new()
+        } else {
+            self.runtime
+                .block_on(async { self.service.get_vertex_ids_by_names(names).await })?
+        };
+
+        Ok(inputs.into_iter().map(|v| v.resolve(&ids)).collect())
+    }
+
+    // optionally write scores back to vertexes, and turn keys into hex strings
+    fn finish_scores(
+        &self,
+        scores: HashMap<ObjectId, f64>,
+        write_to: Option<&str>,
+    ) -> PyResult<HashMap<String, f64>> {
+        if let Some(field) = write_to {
+            self.runtime
+                .block_on(async { self.service.write_vertex_scores(field, &scores).await })?;
+        }
+
+        Ok(scores.into_iter().map(|(k, v)| (k.to_hex(), v)).collect())
+    }
+}
+
+#[pymethods]
+impl PyGraph {
+    #[new]
+    fn new_graph(uri: String, database: String, category: String) -> PyResult<PyGraph> {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let service =
+            runtime.block_on(async move { GraphService::new(&uri, &database, &category).await })?;
+
+        Ok(PyGraph { service, runtime })
+    }
+
+    /// from a JSON config file if `path` is given, otherwise from `MONGO_*`
+    /// environment variables
+    #[staticmethod]
+    fn from_config(category: String, path: Option<&str>) -> PyResult<PyGraph> {
+        let config = MongoConfig::load(path)?;
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let service =
+            runtime.block_on(async move { GraphService::with_config(&config, &category).await })?;
+
+        Ok(PyGraph { service, runtime })
+    }
+
+    /// like `from_config`, on behalf of the caller of `api_key` whose
+    /// operations are checked against the policy file at `policy`
+    #[staticmethod]
+    fn with_access(
+        category: String,
+        api_key: String,
+        policy: &str,
+        path: Option<&str>,
+    ) -> PyResult<PyGraph> {
+        let config = MongoConfig::load(path)?;
+        let policy = AccessPolicy::from_file(policy)?;
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let service = runtime.block_on(async move {
+            GraphService::with_access(&config, &category, &policy, &api_key).await
+        })?;
 
         Ok(PyGraph { service, runtime })
     }
@@ -184,8 +1188,19 @@ impl PyGraph {
         Py::new(py, res)
     }
 
-    pub fn create_edge(&self, v: EdgeInput) -> PyResult<Py<Edge>> {
-        let dto = EdgeDto::try_from(&v)?;
+    /// the edge is only valid within `[valid_from, valid_to)` if given, endpoints
+    /// are looked up by name if `v.by_name`
+    #[args(valid_from = "None", valid_to = "None")]
+    pub fn create_edge(
+        &self,
+        v: EdgeInput,
+        valid_from: Option<&PyAny>,
+        valid_to: Option<&PyAny>,
+    ) -> PyResult<Py<Edge>> {
+        let dto = self.edge_dtos([&v])?.remove(0)?.valid_between(
+            valid_from.map(py_to_datetime).transpose()?,
+            valid_to.map(py_to_datetime).transpose()?,
+        );
         let res = self
             .runtime
             .block_on(async { self.service.create_edge(dto).await })?;
@@ -195,16 +1210,267 @@ impl PyGraph {
         Py::new(py, res)
     }
 
+    pub fn get_vertex_by_name(&self, name: &str) -> PyResult<Py<Vertex>> {
+        let res = self
+            .runtime
+            .block_on(async { self.service.get_vertex_by_name(name).await })?;
+
+        let gil = Python::acquire_gil();
+        let py = gil.python();
+        Py::new(py, res)
+    }
+
+    /// the vertex named `name`, created if there is none
+    pub fn get_or_create_vertex(&self, name: &str) -> PyResult<Py<Vertex>> {
+        let dto = VertexDto::new(name);
+        let res = self
+            .runtime
+            .block_on(async { self.service.get_or_create_vertex(dto).await })?;
+
+        let gil = Python::acquire_gil();
+        let py = gil.python();
+        Py::new(py, res)
+    }
+
+    /// unique index on vertex names, fails if some names are duplicated already
+    pub fn create_name_index(&self) -> PyResult<()> {
+        self.runtime
+            .block_on(async { self.service.create_name_index().await })?;
+
+        Ok(())
+    }
+
+    pub fn drop_name_index(&self) -> PyResult<()> {
+        self.runtime
+            .block_on(async { self.service.drop_name_index().await })?;
+
+        Ok(())
+    }
+
+    /// create vertexes in one bulk write. Each item of the result is a vertex,
+    /// or the exception of its failure
+    pub fn create_vertexes(&self, names: Vec<String>) -> PyResult<Vec<PyObject>> {
+        let dtos = names.iter().map(|n| VertexDto::new(n)).collect();
+        let res = self
+            .runtime
+            .block_on(async { self.service.create_vertexes(dtos).await })?;
+
+        let gil = Python::acquire_gil();
+        let py = gil.python();
+        Ok(batch_to_py(py, res))
+    }
+
+    /// create edges in one bulk write. Each item of the result is an edge, or
+    /// the exception of its failure
+    pub fn create_edges(&self, edges: Vec<EdgeInput>) -> PyResult<Vec<PyObject>> {
+        let (dtos, slots) = split_batch(self.edge_dtos(&edges)?);
+        let res = self
+            .runtime
+            .block_on(async { self.service.create_edges(dtos).await })?;
+        let res = merge_batch(slots, res);
+
+        let gil = Python::acquire_gil();
+        let py = gil.python();
+        Ok(batch_to_py(py, res))
+    }
+
+    /// update edges concurrently, from `(id, edge)` pairs. Each item of the
+    /// result is the edge before update, or the exception of its failure
+    pub fn update_edges(&self, updates: Vec<(String, EdgeInput)>) -> PyResult<Vec<PyObject>> {
+        let dtos = self.edge_dtos(updates.iter().map(|(_, v)| v))?;
+        let parsed = updates
+            .iter()
+            .zip(dtos)
+            .map(|((id, _), dto)| Ok((ObjectId::from_str(id)?, dto?)))
+            .collect();
+        let (updates, slots) = split_batch(parsed);
+        let res = self
+            .runtime
+            .block_on(async { self.service.update_edges(updates).await })?;
+        let res = merge_batch(slots, res);
+
+        let gil = Python::acquire_gil();
+        let py = gil.python();
+        Ok(batch_to_py(py, res))
+    }
+
+    /// delete vertexes and their edges in bulk writes. Each item of the result
+    /// is `None`, or the exception of its failure
+    pub fn delete_vertexes(&self, ids: Vec<String>) -> PyResult<Vec<PyObject>> {
+        let parsed = ids.iter().map(|id| Ok(ObjectId::from_str(id)?)).collect();
+        let (ids, slots) = split_batch(parsed);
+        let res = self
+            .runtime
+            .block_on(async { self.service.delete_vertexes(ids).await })?;
+        let res = merge_batch(slots, res);
+
+        let gil = Python::acquire_gil();
+        let py = gil.python();
+        Ok(batch_to_py(py, res))
+    }
+
+    /// only edges valid at `at` are traversed, if given
     pub fn get_graph(
         &self,
         vertex_id: String,
         label: Option<&str>,
         depth: Option<i32>,
+        at: Option<&PyAny>,
     ) -> PyResult<Py<GraphOutput>> {
+        let at = at.map(py_to_datetime).transpose()?;
         let (edges, vertexes) = self.runtime.block_on(async {
             let oid = ObjectId::from_str(&vertex_id)?;
             self.service
-                .get_graph_from_vertex_by_label(oid, label, depth)
+                .get_graph_from_vertex_by_label(oid, label, depth, at)
+                .await
+        })?;
+
+        let res = GraphOutput { vertexes, edges };
+
+        let gil = Python::acquire_gil();
+        let py = gil.python();
+        Py::new(py, res)
+    }
+
+    /// a hyperedge among `members`, given as `(vertex_id, role)` pairs
+    #[args(label = "None", properties = "None")]
+    pub fn create_hyperedge(
+        &self,
+        members: Vec<(String, String)>,
+        label: Option<&str>,
+        properties: Option<&PyDict>,
+    ) -> PyResult<Py<HyperEdge>> {
+        let dto = Self::hyperedge_dto(&members, label, properties)?;
+        let res = self
+            .runtime
+            .block_on(async { self.service.create_hyperedge(dto).await })?;
+
+        let gil = Python::acquire_gil();
+        let py = gil.python();
+        Py::new(py, res)
+    }
+
+    pub fn get_hyperedge(&self, id: &str) -> PyResult<Py<HyperEdge>> {
+        let res = self.runtime.block_on(async {
+            let oid = ObjectId::from_str(id)?;
+            self.service.get_hyperedge(oid).await
+        })?;
+
+        let gil = Python::acquire_gil();
+        let py = gil.python();
+        Py::new(py, res)
+    }
+
+    /// replace label & members of a hyperedge, and properties if given;
+    /// returns the hyperedge before the update
+    #[args(label = "None", properties = "None")]
+    pub fn update_hyperedge(
+        &self,
+        id: &str,
+        members: Vec<(String, String)>,
+        label: Option<&str>,
+        properties: Option<&PyDict>,
+    ) -> PyResult<Py<HyperEdge>> {
+        let oid = ObjectId::from_str(id).map_err(Pyo3MongoError::from)?;
+        let dto = Self::hyperedge_dto(&members, label, properties)?;
+        let res = self
+            .runtime
+            .block_on(async { self.service.update_hyperedge(oid, dto).await })?;
+
+        let gil = Python::acquire_gil();
+        let py = gil.python();
+        Py::new(py, res)
+    }
+
+    /// hyperedges the vertex is a member of, under `role` if given
+    #[args(role = "None")]
+    pub fn get_hyperedges(
+        &self,
+        vertex_id: &str,
+        role: Option<&str>,
+    ) -> PyResult<Vec<Py<HyperEdge>>> {
+        let res = self.runtime.block_on(async {
+            let oid = ObjectId::from_str(vertex_id)?;
+            self.service.get_hyperedges_by_vertex(oid, role).await
+        })?;
+
+        let gil = Python::acquire_gil();
+        let py = gil.python();
+        res.into_iter().map(|h| Py::new(py, h)).collect()
+    }
+
+    pub fn delete_hyperedge(&self, id: &str) -> PyResult<()> {
+        self.runtime.block_on(async {
+            let oid = ObjectId::from_str(id)?;
+            self.service.delete_hyperedge(oid).await
+        })?;
+
+        Ok(())
+    }
+
+    /// `(hyperedges, vertexes)` reachable from a vertex, see `get_graph`
+    #[args(label = "None", depth = "None")]
+    pub fn get_hypergraph(
+        &self,
+        vertex_id: &str,
+        label: Option<&str>,
+        depth: Option<i32>,
+    ) -> PyResult<(Vec<HyperEdge>, Vec<Vertex>)> {
+        let res = self.runtime.block_on(async {
+            let oid = ObjectId::from_str(vertex_id)?;
+            self.service
+                .get_graph_from_vertex_by_hyperedges(oid, label, depth)
+                .await
+        })?;
+
+        Ok(res)
+    }
+
+    /// text index on vertex names and the given properties, replacing the
+    /// previous one
+    pub fn create_text_index(&self, properties: Option<Vec<&str>>) -> PyResult<()> {
+        let properties = properties.unwrap_or_default();
+        self.runtime
+            .block_on(async { self.service.create_text_index(&properties).await })?;
+
+        Ok(())
+    }
+
+    pub fn drop_text_index(&self) -> PyResult<()> {
+        self.runtime
+            .block_on(async { self.service.drop_text_index().await })?;
+
+        Ok(())
+    }
+
+    /// `(vertex, score)` pairs matching `text`, the most relevant first
+    #[args(limit = "10")]
+    pub fn search(&self, text: &str, limit: i64) -> PyResult<Vec<(Py<Vertex>, f64)>> {
+        let res = self
+            .runtime
+            .block_on(async { self.service.search(text, limit).await })?;
+
+        let gil = Python::acquire_gil();
+        let py = gil.python();
+        res.into_iter()
+            .map(|(v, score)| Ok((Py::new(py, v)?, score)))
+            .collect()
+    }
+
+    /// like `get_graph`, but traversals start from the hits of `search`
+    #[args(limit = "10", label = "None", depth = "None", at = "None")]
+    pub fn get_graph_from_search(
+        &self,
+        text: &str,
+        limit: i64,
+        label: Option<&str>,
+        depth: Option<i32>,
+        at: Option<&PyAny>,
+    ) -> PyResult<Py<GraphOutput>> {
+        let at = at.map(py_to_datetime).transpose()?;
+        let (edges, vertexes) = self.runtime.block_on(async {
+            self.service
+                .get_graph_from_search(text, limit, label, depth, at)
                 .await
         })?;
 
@@ -214,14 +1480,628 @@ impl PyGraph {
         let py = gil.python();
         Py::new(py, res)
     }
+
+    /// vertexes & edges within `hops` of a vertex, edges are sampled by weight
+    /// beyond the limits (`None` for unlimited)
+    #[args(
+        hops = "1",
+        label = "None",
+        per_hop_limit = "1_000",
+        total_limit = "10_000",
+        at = "None"
+    )]
+    pub fn neighborhood(
+        &self,
+        vertex_id: &str,
+        hops: u32,
+        label: Option<&str>,
+        per_hop_limit: Option<usize>,
+        total_limit: Option<usize>,
+        at: Option<&PyAny>,
+    ) -> PyResult<Py<Neighborhood>> {
+        let vertex_id = ObjectId::from_str(vertex_id).map_err(Pyo3MongoError::from)?;
+        let options = NeighborhoodOptions {
+            hops,
+            label,
+            at: at.map(py_to_datetime).transpose()?,
+            per_hop_limit,
+            total_limit,
+        };
+        let res = self
+            .runtime
+            .block_on(async { self.service.neighborhood(vertex_id, options).await })?;
+
+        let gil = Python::acquire_gil();
+        let py = gil.python();
+        Py::new(py, res)
+    }
+
+    /// health check, returns the round trip time in milliseconds
+    pub fn ping(&self) -> PyResult<f64> {
+        let elapsed = self.runtime.block_on(async { self.service.ping().await })?;
+
+        Ok(elapsed.as_secs_f64() * 1000.0)
+    }
+
+    /// keep vertex lookups & traversals in a read-through cache of `capacity`
+    /// entries each, expiring after `ttl` seconds (never if `None`). Any write
+    /// made through this graph clears it. `capacity=0` drops the cache.
+    #[args(capacity = "1024", ttl = "None")]
+    pub fn set_cache(&mut self, capacity: usize, ttl: Option<f64>) -> PyResult<()> {
+        let ttl = ttl
+            .map(|s| {
+                Duration::try_from_secs_f64(s)
+                    .map_err(|_| PyValueError::new_err("ttl must be a non-negative number"))
+            })
+            .transpose()?;
+        let options = (capacity > 0).then_some(CacheOptions { capacity, ttl });
+        self.service.set_cache(options);
+
+        Ok(())
+    }
+
+    /// run idempotent operations (lookups, single creates/updates/deletes) up
+    /// to `max_attempts` times on transient failures, waiting `initial_backoff`
+    /// seconds, doubled at each retry up to `max_backoff`, minus up to `jitter`
+    /// of it at random. `max_attempts=1` never retries.
+    #[args(
+        max_attempts = "3",
+        initial_backoff = "0.1",
+        max_backoff = "5.0",
+        jitter = "0.5"
+    )]
+    pub fn set_retry(
+        &mut self,
+        max_attempts: u32,
+        initial_backoff: f64,
+        max_backoff: f64,
+        jitter: f64,
+    ) -> PyResult<()> {
+        if max_attempts == 0 || initial_backoff < 0.0 || max_backoff < 0.0 {
+            return Err(PyValueError::new_err(
+                "max_attempts must be positive, backoffs non-negative",
+            ));
+        }
+        if !(0.0..=1.0).contains(&jitter) {
+            return Err(PyValueError::new_err("jitter must be within [0, 1]"));
+        }
+        self.service.set_retry(RetryPolicy {
+            max_attempts,
+            initial_backoff_ms: (initial_backoff * 1000.0) as u64,
+            max_backoff_ms: (max_backoff * 1000.0) as u64,
+            jitter,
+            ..Default::default()
+        });
+
+        Ok(())
+    }
+
+    /// hit/miss metrics of the cache, `None` if there is none
+    pub fn cache_stats(&self) -> PyResult<Option<Py<CacheStats>>> {
+        let gil = Python::acquire_gil();
+        let py = gil.python();
+        self.service
+            .cache_stats()
+            .map(|stats| Py::new(py, stats))
+            .transpose()
+    }
+
+    pub fn stats(&self) -> PyResult<Py<GraphStats>> {
+        let res = self
+            .runtime
+            .block_on(async { self.service.stats().await })?;
+
+        let gil = Python::acquire_gil();
+        let py = gil.python();
+        Py::new(py, res)
+    }
+
+    /// merge vertex `remove` into vertex `keep`, `strategy` is one of
+    /// "prefer_keep", "prefer_remove" and "fail"
+    #[args(strategy = "\"prefer_keep\"")]
+    pub fn merge_vertexes(&self, keep: &str, remove: &str, strategy: &str) -> PyResult<Py<Vertex>> {
+        let keep = ObjectId::from_str(keep).map_err(Pyo3MongoError::from)?;
+        let remove = ObjectId::from_str(remove).map_err(Pyo3MongoError::from)?;
+        let strategy = MergeStrategy::from_str(strategy)?;
+        let res = self
+            .runtime
+            .block_on(async { self.service.merge_vertexes(keep, remove, strategy).await })?;
+
+        let gil = Python::acquire_gil();
+        let py = gil.python();
+        Py::new(py, res)
+    }
+
+    pub fn check_integrity(&self) -> PyResult<Py<IntegrityReport>> {
+        let res = self
+            .runtime
+            .block_on(async { self.service.check_integrity().await })?;
+
+        let gil = Python::acquire_gil();
+        let py = gil.python();
+        Py::new(py, res)
+    }
+
+    /// offenders are deleted, or quarantined if `quarantine`; nothing is
+    /// touched unless `dry_run` is turned off
+    #[args(quarantine = "false", dry_run = "true")]
+    pub fn repair(&self, quarantine: bool, dry_run: bool) -> PyResult<Py<IntegrityReport>> {
+        let action = if quarantine {
+            RepairAction::Quarantine
+        } else {
+            RepairAction::Delete
+        };
+        let res = self
+            .runtime
+            .block_on(async { self.service.repair(action, dry_run).await })?;
+
+        let gil = Python::acquire_gil();
+        let py = gil.python();
+        Py::new(py, res)
+    }
+
+    /// what turns this graph into the one of `other_category`, vertexes &
+    /// edges being paired by `key`: "id", or "natural" (vertexes by name,
+    /// edges by endpoints & label)
+    #[args(key = "\"id\"")]
+    pub fn diff(&self, other_category: &str, key: &str) -> PyResult<Py<GraphDiff>> {
+        let key = DiffKey::from_str(key)?;
+        let res = self
+            .runtime
+            .block_on(async { self.service.diff(other_category, key).await })?;
+
+        let gil = Python::acquire_gil();
+        let py = gil.python();
+        Py::new(py, res)
+    }
+
+    /// sync this graph as told by `diff`, computed against it; nothing is
+    /// touched unless `dry_run` is turned off
+    #[args(dry_run = "true")]
+    pub fn apply_diff(&self, diff: PyRef<GraphDiff>, dry_run: bool) -> PyResult<()> {
+        self.runtime
+            .block_on(async { self.service.apply_diff(&diff, dry_run).await })?;
+
+        Ok(())
+    }
+
+    /// total & average weight of outgoing edges per vertex, of `vertex_ids` if
+    /// given, heaviest first; a missing weight counts as 1
+    #[args(vertex_ids = "None", label = "None", at = "None")]
+    pub fn out_weights(
+        &self,
+        vertex_ids: Option<Vec<String>>,
+        label: Option<&str>,
+        at: Option<&PyAny>,
+    ) -> PyResult<Vec<VertexWeight>> {
+        let at = at.map(py_to_datetime).transpose()?;
+        let ids = vertex_ids
+            .map(|ids| {
+                ids.iter()
+                    .map(|id| ObjectId::from_str(id))
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .transpose()
+            .map_err(Pyo3MongoError::from)?;
+        let res = self
+            .runtime
+            .block_on(async { self.service.out_weights(ids, label, at).await })?;
+
+        Ok(res)
+    }
+
+    /// count, total, average, min, max & standard deviation of edge weights,
+    /// per label
+    #[args(at = "None")]
+    pub fn label_weights(&self, at: Option<&PyAny>) -> PyResult<Vec<LabelWeights>> {
+        let at = at.map(py_to_datetime).transpose()?;
+        let res = self
+            .runtime
+            .block_on(async { self.service.label_weights(at).await })?;
+
+        Ok(res)
+    }
+
+    /// weighted adjacency matrix of `vertex_ids`, parallel edges summed up
+    #[args(label = "None", at = "None")]
+    pub fn adjacency_matrix(
+        &self,
+        vertex_ids: Vec<String>,
+        label: Option<&str>,
+        at: Option<&PyAny>,
+    ) -> PyResult<Py<AdjacencyMatrix>> {
+        let at = at.map(py_to_datetime).transpose()?;
+        let ids = vertex_ids
+            .iter()
+            .map(|id| ObjectId::from_str(id))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(Pyo3MongoError::from)?;
+        let res = self
+            .runtime
+            .block_on(async { self.service.adjacency_matrix(ids, label, at).await })?;
+
+        let gil = Python::acquire_gil();
+        let py = gil.python();
+        Py::new(py, res)
+    }
+
+    /// install `$jsonSchema` validators on the vertex, edge & hyperedge
+    /// collections, a non-conforming write is rejected, or only logged by Mongo
+    /// if `warn`
+    #[args(warn = "false")]
+    pub fn install_validators(&self, warn: bool) -> PyResult<()> {
+        let action = if warn {
+            ValidationAction::Warn
+        } else {
+            ValidationAction::Error
+        };
+        self.runtime
+            .block_on(async { self.service.install_validators(action).await })?;
+
+        Ok(())
+    }
+
+    pub fn remove_validators(&self) -> PyResult<()> {
+        self.runtime
+            .block_on(async { self.service.remove_validators().await })?;
+
+        Ok(())
+    }
+
+    /// existing documents which do not conform to the schemas
+    pub fn validate(&self) -> PyResult<Py<ValidationReport>> {
+        let res = self
+            .runtime
+            .block_on(async { self.service.validate().await })?;
+
+        let gil = Python::acquire_gil();
+        let py = gil.python();
+        Py::new(py, res)
+    }
+
+    /// bulk load a networkx graph. A node's `name` attribute (or the node
+    /// itself) becomes the vertex name, other attributes become properties.
+    /// Edges keep their `weight` & `label` attributes, and are stored once
+    /// even if the graph is undirected.
+    #[args(name = "\"name\"")]
+    pub fn from_networkx(&self, graph: &PyAny, name: &str) -> PyResult<Py<GraphOutput>> {
+        let py = graph.py();
+        let data = PyDict::new(py);
+        data.set_item("data", true)?;
+
+        // node -> ObjectId
+        let ids = PyDict::new(py);
+        let mut vertexes = Vec::new();
+        for item in graph.call_method("nodes", (), Some(data))?.iter()? {
+            let (node, attrs): (&PyAny, &PyDict) = item?.extract()?;
+            let attrs = attrs.copy()?;
+            let vertex_name = match attrs.get_item(name) {
+                Some(n) => n.str()?.to_str()?.to_owned(),
+                None => node.str()?.to_str()?.to_owned(),
+            };
+            if attrs.contains(name)? {
+                attrs.del_item(name)?;
+            }
+            let properties = match py_to_bson(attrs)? {
+                Bson::Document(d) if !d.is_empty() => Some(d),
+                _ => None,
+            };
+
+            let id = ObjectId::new();
+            ids.set_item(node, id.to_hex())?;
+            vertexes.push(Vertex {
+                id: Some(id),
+                name: vertex_name,
+                properties,
+            });
+        }
+
+        let mut edges = Vec::new();
+        for item in graph.call_method("edges", (), Some(data))?.iter()? {
+            let (u, v, attrs): (&PyAny, &PyAny, &PyDict) = item?.extract()?;
+            let oid = |node: &PyAny| -> PyResult<ObjectId> {
+                let hex: String = ids.get_item(node).unwrap().extract()?;
+                Ok(ObjectId::from_str(&hex).unwrap())
+            };
+            edges.push(Edge {
+                id: None,
+                source: oid(u)?,
+                target: oid(v)?,
+                weight: attrs.get_item("weight").map(|w| w.extract()).transpose()?,
+                label: attrs
+                    .get_item("label")
+                    .map(|l| l.str().map(|l| l.to_string()))
+                    .transpose()?,
+                valid_from: attrs
+                    .get_item("valid_from")
+                    .map(py_to_datetime)
+                    .transpose()?,
+                valid_to: attrs.get_item("valid_to").map(py_to_datetime).transpose()?,
+            });
+        }
+
+        let (edges, vertexes) = self
+            .runtime
+            .block_on(async { self.service.insert_graph(vertexes, edges).await })?;
+
+        Py::new(py, GraphOutput { vertexes, edges })
+    }
+
+    /// match a pattern, each binding is a dict of variable to `Vertex` or `Edge`
+    pub fn query(&self, pattern: &str) -> PyResult<Vec<HashMap<String, PyObject>>> {
+        let bindings = self
+            .runtime
+            .block_on(async { self.service.query(pattern).await })?;
+
+        let gil = Python::acquire_gil();
+        let py = gil.python();
+        let mut res = Vec::with_capacity(bindings.len());
+        for binding in bindings {
+            let mut b = HashMap::new();
+            for (k, v) in binding {
+                let v = match v {
+                    BindingValue::Vertex(v) => Py::new(py, v)?.into_py(py),
+                    BindingValue::Edge(e) => Py::new(py, e)?.into_py(py),
+                };
+                b.insert(k, v);
+            }
+            res.push(b);
+        }
+
+        Ok(res)
+    }
+
+    #[args(damping = "0.85", max_iter = "100", tolerance = "1e-6")]
+    #[allow(clippy::too_many_arguments)]
+    pub fn pagerank(
+        &self,
+        vertex_id: Option<&str>,
+        label: Option<&str>,
+        depth: Option<i32>,
+        damping: f64,
+        max_iter: usize,
+        tolerance: f64,
+        write_to: Option<&str>,
+    ) -> PyResult<HashMap<String, f64>> {
+        let scope = Self::scope(vertex_id, label, depth)?;
+        let options = PageRankOptions {
+            damping,
+            max_iter,
+            tolerance,
+        };
+        let scores = self
+            .runtime
+            .block_on(async { self.service.pagerank(scope, options).await })?;
+
+        self.finish_scores(scores, write_to)
+    }
+
+    #[args(normalized = "true")]
+    pub fn betweenness(
+        &self,
+        vertex_id: Option<&str>,
+        label: Option<&str>,
+        depth: Option<i32>,
+        normalized: bool,
+        write_to: Option<&str>,
+    ) -> PyResult<HashMap<String, f64>> {
+        let scope = Self::scope(vertex_id, label, depth)?;
+        let scores = self
+            .runtime
+            .block_on(async { self.service.betweenness_centrality(scope, normalized).await })?;
+
+        self.finish_scores(scores, write_to)
+    }
+
+    pub fn closeness(
+        &self,
+        vertex_id: Option<&str>,
+        label: Option<&str>,
+        depth: Option<i32>,
+        write_to: Option<&str>,
+    ) -> PyResult<HashMap<String, f64>> {
+        let scope = Self::scope(vertex_id, label, depth)?;
+        let scores = self
+            .runtime
+            .block_on(async { self.service.closeness_centrality(scope).await })?;
+
+        self.finish_scores(scores, write_to)
+    }
+
+    /// random walks from each vertex of the category, or of the graph
+    /// traversed from `vertex_id`. Uniform by default, biased by edge weights
+    /// if `weighted`, and by `p` & `q` as in node2vec.
+    #[args(
+        vertex_id = "None",
+        label = "None",
+        depth = "None",
+        length = "80",
+        walks_per_vertex = "10",
+        p = "1.0",
+        q = "1.0",
+        weighted = "false",
+        seed = "None"
+    )]
+    #[allow(clippy::too_many_arguments)]
+    pub fn random_walks(
+        &self,
+        vertex_id: Option<&str>,
+        label: Option<&str>,
+        depth: Option<i32>,
+        length: usize,
+        walks_per_vertex: usize,
+        p: f64,
+        q: f64,
+        weighted: bool,
+        seed: Option<u64>,
+    ) -> PyResult<Py<Walks>> {
+        let scope = Self::scope(vertex_id, label, depth)?;
+        let options = WalkOptions {
+            length,
+            walks_per_vertex,
+            p,
+            q,
+            weighted,
+            seed,
+        };
+        let res = self
+            .runtime
+            .block_on(async { self.service.random_walks(scope, options).await })?;
+
+        let gil = Python::acquire_gil();
+        let py = gil.python();
+        Py::new(py, res)
+    }
+
+    /// `size` vertexes picked uniformly at random
+    pub fn sample_vertexes(&self, size: usize) -> PyResult<Vec<Py<Vertex>>> {
+        let res = self
+            .runtime
+            .block_on(async { self.service.sample_vertexes(size).await })?;
+
+        let gil = Python::acquire_gil();
+        let py = gil.python();
+        res.into_iter().map(|v| Py::new(py, v)).collect()
+    }
+
+    /// `size` edges picked uniformly at random
+    #[args(label = "None", at = "None")]
+    pub fn sample_edges(
+        &self,
+        size: usize,
+        label: Option<&str>,
+        at: Option<&PyAny>,
+    ) -> PyResult<Vec<Py<Edge>>> {
+        let at = at.map(py_to_datetime).transpose()?;
+        let res = self
+            .runtime
+            .block_on(async { self.service.sample_edges(size, label, at).await })?;
+
+        let gil = Python::acquire_gil();
+        let py = gil.python();
+        res.into_iter().map(|e| Py::new(py, e)).collect()
+    }
+
+    /// snowball sample from `vertex_id`, following at most `per_vertex`
+    /// outgoing edges of each vertex reached, for `waves` hops
+    #[args(
+        waves = "2",
+        per_vertex = "10",
+        label = "None",
+        at = "None",
+        seed = "None"
+    )]
+    pub fn snowball_sample(
+        &self,
+        vertex_id: &str,
+        waves: u32,
+        per_vertex: usize,
+        label: Option<&str>,
+        at: Option<&PyAny>,
+        seed: Option<u64>,
+    ) -> PyResult<Py<Sample>> {
+        let vertex_id = ObjectId::from_str(vertex_id).map_err(Pyo3MongoError::from)?;
+        let options = SnowballOptions {
+            waves,
+            per_vertex,
+            label,
+            at: at.map(py_to_datetime).transpose()?,
+            seed,
+        };
+        let res = self
+            .runtime
+            .block_on(async { self.service.snowball_sample(vertex_id, options).await })?;
+
+        let gil = Python::acquire_gil();
+        let py = gil.python();
+        Py::new(py, res)
+    }
+}
+
+/// metrics of graph operations in the Prometheus text format, see `telemetry`
+#[pyfunction]
+fn metrics() -> String {
+    telemetry::render()
+}
+
+/// log graph operations to stderr, filtered by `RUST_LOG` if set, otherwise by
+/// `filter`. False if already initialized.
+#[pyfunction(filter = "\"p3m=info\"")]
+fn init_tracing(filter: &str) -> bool {
+    telemetry::init_tracing(filter)
 }
 
 #[pymodule]
 fn p3m(_py: Python, m: &PyModule) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(metrics, m)?)?;
+    m.add_function(wrap_pyfunction!(init_tracing, m)?)?;
     m.add_class::<Vertex>()?;
     m.add_class::<Edge>()?;
+    m.add_class::<HyperEdge>()?;
     m.add_class::<EdgeInput>()?;
     m.add_class::<GraphOutput>()?;
+    m.add_class::<GraphStats>()?;
+    m.add_class::<GraphDiff>()?;
+    m.add_class::<VertexChange>()?;
+    m.add_class::<EdgeChange>()?;
+    m.add_class::<CacheStats>()?;
+    m.add_class::<CacheMetrics>()?;
+    m.add_class::<IndexStatus>()?;
+    m.add_class::<IntegrityReport>()?;
+    m.add_class::<Neighborhood>()?;
+    m.add_class::<ValidationReport>()?;
+    m.add_class::<VertexWeight>()?;
+    m.add_class::<LabelWeights>()?;
+    m.add_class::<AdjacencyMatrix>()?;
+    m.add_class::<Walks>()?;
+    m.add_class::<Sample>()?;
     m.add_class::<PyGraph>()?;
     Ok(())
 }
+
+#[cfg(test)]
+mod test_package {
+    use super::*;
+
+    // round trip of a bson value through Python
+    fn round_trip(value: Bson) -> Bson {
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let object = bson_to_py(py, &value);
+            py_to_bson(object.as_ref(py)).unwrap()
+        })
+    }
+
+    #[test]
+    fn test_bson_round_trip() {
+        // integers come back as `Int64`, Python having a single int type
+        let value = Bson::Document(doc! {
+            "name": "node-1",
+            "weight": 1.5,
+            "count": 3_i64,
+            "active": true,
+            "missing": Bson::Null,
+            "at": DateTime::from_millis(1_650_000_000_123),
+            "nested": {"tags": ["a", ["b", 2_i64]], "inner": {"x": -1_i64}},
+        });
+        assert_eq!(round_trip(value.clone()), value);
+        assert_eq!(round_trip(Bson::Int32(3)), Bson::Int64(3));
+
+        // ObjectIds are hex strings
+        let id = ObjectId::new();
+        assert_eq!(round_trip(Bson::ObjectId(id)), Bson::String(id.to_hex()));
+        let value = Bson::Array(vec![Bson::ObjectId(id), Bson::Array(vec![])]);
+        assert_eq!(
+            round_trip(value),
+            Bson::Array(vec![Bson::String(id.to_hex()), Bson::Array(vec![])])
+        );
+    }
+
+    #[test]
+    fn test_weight_bits() {
+        assert_eq!(weight_bits(Some(-0.0)), weight_bits(Some(0.0)));
+        assert_eq!(weight_bits(Some(f64::NAN)), weight_bits(Some(-f64::NAN)));
+        assert_ne!(weight_bits(Some(1.0)), weight_bits(Some(-1.0)));
+        assert_eq!(weight_bits(None), None);
+    }
+}
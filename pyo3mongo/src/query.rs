@@ -0,0 +1,393 @@
+//! Query
+//!
+//! A small Cypher-like pattern language, compiled into a Mongo aggregation
+//! pipeline against `${cat}_vertex` & `${cat}_edge`:
+//!
+//! ```text
+//! (a {name: "x"})-[e:label*1..3]->(b)
+//! (a)<-[:label]-(b {kind: "supplier"})
+//! (a {id: "62a5b3c2f1e0a1b2c3d4e5f6"})
+//! ```
+//!
+//! - a node is `(variable {key: value, ...})`, both parts are optional. `name`
+//!   & `id` are matched against `Vertex.name` & `Vertex.id`, any other key is
+//!   matched against `Vertex.properties`.
+//! - a relationship is `-[variable:label*min..max]->` (or `<-[...]-` for the
+//!   reversed direction), every part is optional. A missing range means exactly
+//!   one hop, `*` alone means one hop or more.
+//!
+//! Since `$graphLookup` does not keep paths, a relationship variable is bound
+//! to the last edge of the path, i.e. the one which reaches `b`.
+
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use mongodb::bson::oid::ObjectId;
+use mongodb::bson::{doc, Bson, Document};
+use nom::branch::alt;
+use nom::bytes::complete::{tag, take_while, take_while1};
+use nom::character::complete::{char, digit1, multispace0};
+use nom::combinator::{all_consuming, map, map_res, opt, value};
+use nom::multi::separated_list0;
+use nom::number::complete::double;
+use nom::sequence::{delimited, pair, preceded, separated_pair, terminated, tuple};
+use nom::IResult;
+
+use crate::{Edge, Pyo3MongoError, Pyo3MongoResult, Vertex};
+
+// fields of intermediate documents in the pipeline
+const EDGES: &str = "_edges";
+const DEPTH: &str = "_depth";
+const TARGET: &str = "_target";
+
+/// `(variable {key: value})`
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct NodePattern {
+    pub variable: Option<String>,
+    pub properties: Vec<(String, Bson)>,
+}
+
+/// orientation of a relationship
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    /// `-[]->`, from source to target
+    Outgoing,
+    /// `<-[]-`, from target to source
+    Incoming,
+}
+
+/// `-[variable:label*min..max]->`
+#[derive(Debug, Clone, PartialEq)]
+pub struct RelPattern {
+    pub variable: Option<String>,
+    pub label: Option<String>,
+    pub min_hops: u32,
+    /// `None` means unbounded
+    pub max_hops: Option<u32>,
+    pub direction: Direction,
+}
+
+/// a node, optionally followed by a relationship to another node
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pattern {
+    pub start: NodePattern,
+    pub rel: Option<(RelPattern, NodePattern)>,
+}
+
+/// a value bound to a pattern variable
+#[derive(Debug, Clone, PartialEq)]
+pub enum BindingValue {
+    Vertex(Vertex),
+    Edge(Edge),
+}
+
+/// pattern variables and their values, one for each match
+pub type Binding = HashMap<String, BindingValue>;
+
+type ParseResult<'a, T> = IResult<&'a str, T>;
+
+fn ws<'a, T>(
+    inner: impl FnMut(&'a str) -> ParseResult<'a, T>,
+) -> impl FnMut(&'a str) -> ParseResult<'a, T> {
+    delimited(multispace0, inner, multispace0)
+}
+
+fn identifier(input: &str) -> ParseResult<'_, &str> {
+    take_while1(|c: char| c.is_alphanumeric() || c == '_' || c == '-')(input)
+}
+
+fn string_literal(input: &str) -> ParseResult<'_, &str> {
+    alt((
+        delimited(char('"'), take_while(|c| c != '"'), char('"')),
+        delimited(char('\''), take_while(|c| c != '\''), char('\'')),
+    ))(input)
+}
+
+fn literal(input: &str) -> ParseResult<'_, Bson> {
+    alt((
+        map(string_literal, |s| Bson::String(s.to_owned())),
+        value(Bson::Boolean(true), tag("true")),
+        value(Bson::Boolean(false), tag("false")),
+        value(Bson::Null, tag("null")),
+        map(double, Bson::Double),
+    ))(input)
+}
+
+fn properties(input: &str) -> ParseResult<'_, Vec<(String, Bson)>> {
+    let entry = separated_pair(ws(identifier), char(':'), ws(literal));
+    delimited(
+        char('{'),
+        separated_list0(char(','), map(entry, |(k, v)| (k.to_owned(), v))),
+        char('}'),
+    )(input)
+}
+
+fn node(input: &str) -> ParseResult<'_, NodePattern> {
+    let inner = pair(
+        ws(opt(identifier)),
+        opt(terminated(properties, multispace0)),
+    );
+    map(
+        delimited(char('('), inner, char(')')),
+        |(variable, properties)| NodePattern {
+            variable: variable.map(str::to_owned),
+            properties: properties.unwrap_or_default(),
+        },
+    )(input)
+}
+
+fn hops(input: &str) -> ParseResult<'_, u32> {
+    map_res(digit1, u32::from_str)(input)
+}
+
+// `*`, `*n`, `*n..`, `*..m` or `*n..m`
+fn range(input: &str) -> ParseResult<'_, (u32, Option<u32>)> {
+    let bounded = map(
+        tuple((opt(hops), ws(tag("..")), opt(hops))),
+        |(min, _, max)| (min.unwrap_or(1), max),
+    );
+    let exact = map(hops, |n| (n, Some(n)));
+    map(preceded(ws(char('*')), opt(alt((bounded, exact)))), |r| {
+        r.unwrap_or((1, None))
+    })(input)
+}
+
+type RelDetail<'a> = (Option<&'a str>, Option<&'a str>, (u32, Option<u32>));
+
+// `variable:label*min..max`, everything inside the brackets
+fn rel_detail(input: &str) -> ParseResult<'_, RelDetail<'_>> {
+    let label = preceded(ws(char(':')), alt((string_literal, identifier)));
+    map(
+        tuple((ws(opt(identifier)), opt(label), ws(opt(range)))),
+        |(variable, label, range)| (variable, label, range.unwrap_or((1, Some(1)))),
+    )(input)
+}
+
+fn rel(input: &str) -> ParseResult<'_, RelPattern> {
+    let detail = || delimited(char('['), rel_detail, char(']'));
+    let outgoing = map(delimited(char('-'), detail(), tag("->")), |d| {
+        (d, Direction::Outgoing)
+    });
+    let incoming = map(delimited(tag("<-"), detail(), char('-')), |d| {
+        (d, Direction::Incoming)
+    });
+
+    map(
+        alt((outgoing, incoming)),
+        |((variable, label, (min_hops, max_hops)), direction)| RelPattern {
+            variable: variable.map(str::to_owned),
+            label: label.map(str::to_owned),
+            min_hops,
+            max_hops,
+            direction,
+        },
+    )(input)
+}
+
+fn pattern(input: &str) -> ParseResult<'_, Pattern> {
+    map(
+        pair(ws(node), opt(pair(ws(rel), ws(node)))),
+        |(start, rel)| Pattern { start, rel },
+    )(input)
+}
+
+/// parse a pattern, the whole input must be consumed
+pub fn parse(input: &str) -> Pyo3MongoResult<Pattern> {
+    let (_, p) = all_consuming(pattern)(input)
+        .map_err(|e| Pyo3MongoError::Query(format!("invalid pattern: {}", e)))?;
+
+    if let Some((r, _)) = &p.rel {
+        if r.min_hops == 0 {
+            return Err(Pyo3MongoError::Query("hops start from 1".to_owned()));
+        }
+        if matches!(r.max_hops, Some(max) if max < r.min_hops) {
+            return Err(Pyo3MongoError::Query("invalid range of hops".to_owned()));
+        }
+    }
+
+    Ok(p)
+}
+
+// `$match` conditions of a node, `prefix` is where the vertex is in the document
+fn node_filter(node: &NodePattern, prefix: &str) -> Pyo3MongoResult<Document> {
+    let mut filter = Document::new();
+    for (k, v) in &node.properties {
+        match (k.as_str(), v) {
+            ("id" | "_id", Bson::String(s)) => {
+                let oid = ObjectId::from_str(s)?;
+                filter.insert(format!("{}_id", prefix), oid);
+            }
+            ("id" | "_id", _) => {
+                return Err(Pyo3MongoError::Query("id must be a string".to_owned()))
+            }
+            ("name", _) => {
+                filter.insert(format!("{}name", prefix), v.clone());
+            }
+            _ => {
+                filter.insert(format!("{}properties.{}", prefix, k), v.clone());
+            }
+        }
+    }
+
+    Ok(filter)
+}
+
+/// compile a pattern into a pipeline, which runs against `${cat}_vertex`
+pub fn compile(pattern: &Pattern, cat: &str) -> Pyo3MongoResult<Vec<Document>> {
+    let mut pipeline = vec![doc! {"$match": node_filter(&pattern.start, "")?}];
+
+    let (rel, end) = match &pattern.rel {
+        Some(r) => r,
+        None => return Ok(pipeline),
+    };
+
+    let (connect_from, connect_to) = match rel.direction {
+        Direction::Outgoing => ("target", "source"),
+        Direction::Incoming => ("source", "target"),
+    };
+
+    // depth of an edge found by `$graphLookup` starts from 0, which is one hop
+    let mut graph_lookup = doc! {
+        "from": format!("{}_edge", cat),
+        "startWith": "$_id",
+        "connectFromField": connect_from,
+        "connectToField": connect_to,
+        "as": EDGES,
+        "depthField": DEPTH,
+    };
+    if let Some(max) = rel.max_hops {
+        graph_lookup.insert("maxDepth", max as i64 - 1);
+    }
+    if let Some(label) = &rel.label {
+        graph_lookup.insert("restrictSearchWithMatch", doc! {"label": label});
+    }
+
+    pipeline.push(doc! {"$graphLookup": graph_lookup});
+    pipeline.push(doc! {"$unwind": format!("${}", EDGES)});
+    if rel.min_hops > 1 {
+        let depth = format!("{}.{}", EDGES, DEPTH);
+        pipeline.push(doc! {"$match": {depth: {"$gte": rel.min_hops as i64 - 1}}});
+    }
+    pipeline.push(doc! {"$lookup": {
+        "from": format!("{}_vertex", cat),
+        "localField": format!("{}.{}", EDGES, connect_from),
+        "foreignField": "_id",
+        "as": TARGET,
+    }});
+    pipeline.push(doc! {"$unwind": format!("${}", TARGET)});
+
+    let end_filter = node_filter(end, &format!("{}.", TARGET))?;
+    if !end_filter.is_empty() {
+        pipeline.push(doc! {"$match": end_filter});
+    }
+
+    Ok(pipeline)
+}
+
+/// turn a document yielded by a compiled pipeline into a binding
+pub fn bind(pattern: &Pattern, mut doc: Document) -> Pyo3MongoResult<Binding> {
+    let mut binding = Binding::new();
+
+    let edge = doc.remove(EDGES);
+    let target = doc.remove(TARGET);
+
+    if let Some((rel, end)) = &pattern.rel {
+        if let (Some(var), Some(Bson::Document(mut e))) = (&rel.variable, edge) {
+            e.remove(DEPTH);
+            binding.insert(var.clone(), BindingValue::Edge(bson::from_document(e)?));
+        }
+        if let (Some(var), Some(Bson::Document(v))) = (&end.variable, target) {
+            binding.insert(var.clone(), BindingValue::Vertex(bson::from_document(v)?));
+        }
+    }
+    if let Some(var) = &pattern.start.variable {
+        binding.insert(var.clone(), BindingValue::Vertex(bson::from_document(doc)?));
+    }
+
+    Ok(binding)
+}
+
+#[cfg(test)]
+mod test_query {
+    use super::*;
+
+    #[test]
+    fn test_parse_node() {
+        let p = parse(r#"(a {name: "x", rank: 2})"#).unwrap();
+        assert_eq!(p.start.variable.as_deref(), Some("a"));
+        assert_eq!(
+            p.start.properties,
+            vec![
+                ("name".to_owned(), Bson::String("x".to_owned())),
+                ("rank".to_owned(), Bson::Double(2.0))
+            ]
+        );
+        assert!(p.rel.is_none());
+
+        let p = parse("()").unwrap();
+        assert_eq!(p.start, NodePattern::default());
+    }
+
+    #[test]
+    fn test_parse_rel() {
+        let p = parse(r#"(a {name:"x"})-[:label*1..3]->(b)"#).unwrap();
+        let (rel, end) = p.rel.unwrap();
+        assert_eq!(rel.variable, None);
+        assert_eq!(rel.label.as_deref(), Some("label"));
+        assert_eq!((rel.min_hops, rel.max_hops), (1, Some(3)));
+        assert_eq!(rel.direction, Direction::Outgoing);
+        assert_eq!(end.variable.as_deref(), Some("b"));
+
+        let p = parse("(a) <-[e:'test-label']- (b)").unwrap();
+        let (rel, _) = p.rel.unwrap();
+        assert_eq!(rel.variable.as_deref(), Some("e"));
+        assert_eq!(rel.label.as_deref(), Some("test-label"));
+        assert_eq!((rel.min_hops, rel.max_hops), (1, Some(1)));
+        assert_eq!(rel.direction, Direction::Incoming);
+
+        let ranges = [
+            ("*", (1, None)),
+            ("*2", (2, Some(2))),
+            ("*2..", (2, None)),
+            ("*..4", (1, Some(4))),
+        ];
+        for (r, expected) in ranges {
+            let p = parse(&format!("(a)-[{}]->(b)", r)).unwrap();
+            let (rel, _) = p.rel.unwrap();
+            assert_eq!((rel.min_hops, rel.max_hops), expected);
+        }
+    }
+
+    #[test]
+    fn test_parse_error() {
+        assert!(parse("(a").is_err());
+        assert!(parse("(a)-[:x]-(b)").is_err());
+        assert!(parse("(a)-[*0..2]->(b)").is_err());
+        assert!(parse("(a)-[*3..2]->(b)").is_err());
+    }
+
+    #[test]
+    fn test_compile() {
+        let p = parse(r#"(a {name:"x"})-[:label*2..3]->(b {kind: "y"})"#).unwrap();
+        let pipeline = compile(&p, "dev").unwrap();
+
+        assert_eq!(pipeline[0], doc! {"$match": {"name": "x"}});
+        let graph_lookup = pipeline[1].get_document("$graphLookup").unwrap();
+        assert_eq!(graph_lookup.get_str("from").unwrap(), "dev_edge");
+        assert_eq!(graph_lookup.get_i64("maxDepth").unwrap(), 2);
+        assert_eq!(
+            graph_lookup
+                .get_document("restrictSearchWithMatch")
+                .unwrap(),
+            &doc! {"label": "label"}
+        );
+        assert_eq!(
+            pipeline[3],
+            doc! {"$match": {"_edges._depth": {"$gte": 1i64}}}
+        );
+        assert_eq!(
+            pipeline.last().unwrap(),
+            &doc! {"$match": {"_target.properties.kind": "y"}}
+        );
+    }
+}
@@ -0,0 +1,251 @@
+//! Retry
+//!
+//! A `RetryPolicy` runs idempotent operations of `GraphService` again when
+//! they fail for a transient reason (a network blip, a primary stepping down,
+//! an error labelled retryable by the driver), waiting longer and longer in
+//! between. Other failures are returned at once.
+//!
+//! A transient error which persists until attempts run out is wrapped in
+//! `Retried`, telling their number. An error which is not transient is returned
+//! as it is, even after a retry: a retried lookup still fails with "not found".
+
+use std::collections::hash_map::RandomState;
+use std::future::Future;
+use std::hash::{BuildHasher, Hasher};
+use std::time::Duration;
+
+use mongodb::error::{ErrorKind, RETRYABLE_WRITE_ERROR, TRANSIENT_TRANSACTION_ERROR};
+use serde::{Deserialize, Serialize};
+
+use crate::Pyo3MongoError;
+
+/// codes of server errors which go away by themselves, e.g. during an election
+const TRANSIENT_CODES: &[i32] = &[
+    6,     // HostUnreachable
+    7,     // HostNotFound
+    89,    // NetworkTimeout
+    91,    // ShutdownInProgress
+    189,   // PrimarySteppedDown
+    262,   // ExceededTimeLimit
+    9001,  // SocketException
+    10107, // NotWritablePrimary
+    11600, // InterruptedAtShutdown
+    11602, // InterruptedDueToReplStateChange
+    13435, // NotPrimaryNoSecondaryOk
+    13436, // NotPrimaryOrSecondary
+];
+
+/// whether `e` may go away by running the operation again
+pub fn is_transient(e: &Pyo3MongoError) -> bool {
+    let e = match e {
+        Pyo3MongoError::Mongo(e) => e,
+        _ => return false,
+    };
+    if e.contains_label(RETRYABLE_WRITE_ERROR) || e.contains_label(TRANSIENT_TRANSACTION_ERROR) {
+        return true;
+    }
+    match &*e.kind {
+        ErrorKind::Io(_)
+        | ErrorKind::ConnectionPoolCleared { .. }
+        | ErrorKind::ServerSelection { .. } => true,
+        ErrorKind::Command(c) => TRANSIENT_CODES.contains(&c.code),
+        _ => false,
+    }
+}
+
+/// how many times, and how long apart, an operation is attempted. The n-th
+/// retry waits `initial_backoff_ms * multiplier ^ (n - 1)`, up to
+/// `max_backoff_ms`, minus up to `jitter` of it at random.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(default)]
+pub struct RetryPolicy {
+    /// attempts in total, `1` never retries
+    pub max_attempts: u32,
+    pub initial_backoff_ms: u64,
+    pub max_backoff_ms: u64,
+    pub multiplier: f64,
+    /// within `[0, 1]`, `0` waits exactly the backoff
+    pub jitter: f64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: 1,
+            initial_backoff_ms: 100,
+            max_backoff_ms: 5000,
+            multiplier: 2.0,
+            jitter: 0.5,
+        }
+    }
+}
+
+// uniform within `[0, 1)`, not cryptographically
+fn random() -> f64 {
+    let n = RandomState::new().build_hasher().finish();
+    (n >> 11) as f64 / (1u64 << 53) as f64
+}
+
+impl RetryPolicy {
+    /// `max_attempts` attempts, with default backoff
+    pub fn attempts(max_attempts: u32) -> Self {
+        RetryPolicy {
+            max_attempts,
+            ..Default::default()
+        }
+    }
+
+    /// the wait before the `retry`-th retry (from 1), without jitter
+    pub fn backoff(&self, retry: u32) -> Duration {
+        let exp = self
+            .multiplier
+            .max(1.0)
+            .powi(retry.saturating_sub(1) as i32);
+        let ms = (self.initial_backoff_ms as f64 * exp).min(self.max_backoff_ms as f64);
+        Duration::from_secs_f64(ms / 1000.0)
+    }
+
+    fn wait(&self, retry: u32) -> Duration {
+        let jitter = self.jitter.clamp(0.0, 1.0);
+        self.backoff(retry).mul_f64(1.0 - jitter * random())
+    }
+
+    /// run `call` (given the attempt number, from 1) until it succeeds, fails
+    /// for good, or runs out of attempts. `on_retry` is told about each retry.
+    ///
+    /// `call` must be idempotent: a failed attempt may have been applied.
+    pub async fn run<T, F, Fut>(
+        &self,
+        mut call: F,
+        mut on_retry: impl FnMut(u32, &Pyo3MongoError),
+    ) -> Result<T, Pyo3MongoError>
+    where
+        F: FnMut(u32) -> Fut,
+        Fut: Future<Output = Result<T, Pyo3MongoError>>,
+    {
+        let mut attempt = 1;
+        loop {
+            match call(attempt).await {
+                Err(e) if attempt < self.max_attempts && is_transient(&e) => {
+                    on_retry(attempt, &e);
+                    tokio::time::sleep(self.wait(attempt)).await;
+                    attempt += 1;
+                }
+                Err(e) if attempt > 1 && is_transient(&e) => {
+                    return Err(Pyo3MongoError::Retried {
+                        attempts: attempt,
+                        source: Box::new(e),
+                    })
+                }
+                res => return res,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_retry {
+    use std::io;
+    use std::sync::Arc;
+
+    use super::*;
+
+    fn network_error() -> Pyo3MongoError {
+        let e = io::Error::new(io::ErrorKind::ConnectionReset, "reset");
+        Pyo3MongoError::Mongo(ErrorKind::Io(Arc::new(e)).into())
+    }
+
+    fn policy(max_attempts: u32) -> RetryPolicy {
+        RetryPolicy {
+            initial_backoff_ms: 1,
+            ..RetryPolicy::attempts(max_attempts)
+        }
+    }
+
+    #[test]
+    fn test_transient() {
+        assert!(is_transient(&network_error()));
+        assert!(!is_transient(&Pyo3MongoError::VertexNotFound));
+
+        let de = bson::from_document::<i32>(bson::doc! {}).unwrap_err();
+        let e = mongodb::error::Error::from(ErrorKind::from(de));
+        assert!(!is_transient(&Pyo3MongoError::Mongo(e)));
+    }
+
+    #[test]
+    fn test_backoff() {
+        let policy = RetryPolicy {
+            max_attempts: 5,
+            initial_backoff_ms: 100,
+            max_backoff_ms: 300,
+            multiplier: 2.0,
+            jitter: 0.5,
+        };
+        let ms = |n| policy.backoff(n).as_millis();
+        assert_eq!((ms(1), ms(2), ms(3)), (100, 200, 300));
+
+        for _ in 0..100 {
+            let wait = policy.wait(2).as_millis();
+            assert!((100..=200).contains(&wait), "{}", wait);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_run() {
+        // fails twice, then succeeds
+        let mut retries = Vec::new();
+        let res = policy(3)
+            .run(
+                |attempt| async move {
+                    match attempt {
+                        1 | 2 => Err(network_error()),
+                        n => Ok(n),
+                    }
+                },
+                |attempt, _| retries.push(attempt),
+            )
+            .await;
+        assert_eq!(res.unwrap(), 3);
+        assert_eq!(retries, vec![1, 2]);
+
+        // runs out of attempts
+        let res = policy(2)
+            .run(|_| async { Err::<(), _>(network_error()) }, |_, _| {})
+            .await;
+        match res {
+            Err(Pyo3MongoError::Retried { attempts, source }) => {
+                assert_eq!(attempts, 2);
+                assert!(is_transient(&source));
+            }
+            _ => panic!("unexpected {:?}", res),
+        }
+
+        // not transient, not retried
+        let mut calls = 0;
+        let res = policy(3)
+            .run(
+                |_| {
+                    calls += 1;
+                    async { Err::<(), _>(Pyo3MongoError::VertexNotFound) }
+                },
+                |_, _| {},
+            )
+            .await;
+        assert!(matches!(res, Err(Pyo3MongoError::VertexNotFound)));
+        assert_eq!(calls, 1);
+
+        // not transient after a retry, not wrapped
+        let res = policy(3)
+            .run(
+                |attempt| async move {
+                    match attempt {
+                        1 => Err::<(), _>(network_error()),
+                        _ => Err(Pyo3MongoError::VertexNotFound),
+                    }
+                },
+                |_, _| {},
+            )
+            .await;
+        assert!(matches!(res, Err(Pyo3MongoError::VertexNotFound)));
+    }
+}
@@ -0,0 +1,391 @@
+//! Sampling
+//!
+//! Random walks & samples of a graph, e.g. to train vertex embeddings. Walks
+//! are computed in memory, over vertexes & edges which have already been
+//! fetched from MongoDB (see `GraphService::random_walks`); snowball samples
+//! are drawn hop by hop (see `GraphService::snowball_sample`).
+//!
+//! Results are compact: vertexes are listed once, walks & edges refer to them
+//! by position. Given a seed, they are reproducible.
+
+use std::collections::{HashMap, HashSet};
+
+use mongodb::bson::oid::ObjectId;
+use mongodb::bson::{doc, DateTime, Document};
+use pyo3::prelude::*;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use crate::centrality::Adjacency;
+use crate::{Edge, Pyo3MongoError, Pyo3MongoResult};
+
+/// parameters of random walks, uniform by default.
+///
+/// `p` & `q` bias walks as node2vec does: from `t` to `v`, the next step goes
+/// back to `t` with a weight divided by `p`, to a vertex `t` has no edge to
+/// with a weight divided by `q`.
+#[derive(Debug, Clone, Copy)]
+pub struct WalkOptions {
+    /// number of vertexes of a walk, the starting one included
+    pub length: usize,
+    /// number of walks starting from each vertex
+    pub walks_per_vertex: usize,
+    /// return parameter
+    pub p: f64,
+    /// in-out parameter
+    pub q: f64,
+    /// whether steps are biased by `Edge.weight` (1.0 if absent)
+    pub weighted: bool,
+    /// seed of the random generator, a random one if absent
+    pub seed: Option<u64>,
+}
+
+impl Default for WalkOptions {
+    fn default() -> Self {
+        WalkOptions {
+            length: 80,
+            walks_per_vertex: 10,
+            p: 1.0,
+            q: 1.0,
+            weighted: false,
+            seed: None,
+        }
+    }
+}
+
+impl WalkOptions {
+    pub fn check(&self) -> Pyo3MongoResult<()> {
+        if self.length == 0 {
+            return Err(Pyo3MongoError::Common("walk length must be positive"));
+        }
+        if !(self.p > 0.0 && self.q > 0.0) {
+            return Err(Pyo3MongoError::Common("p & q must be positive"));
+        }
+        Ok(())
+    }
+
+    // whether walks are second order, i.e. depend on the previous vertex
+    fn biased(&self) -> bool {
+        self.p != 1.0 || self.q != 1.0
+    }
+}
+
+/// parameters of `GraphService::snowball_sample`
+#[derive(Debug, Clone, Copy)]
+pub struct SnowballOptions<'a> {
+    /// number of hops, following outgoing edges
+    pub waves: u32,
+    /// max number of edges followed from each vertex
+    pub per_vertex: usize,
+    pub label: Option<&'a str>,
+    /// only edges valid at this instant are followed
+    pub at: Option<DateTime>,
+    /// seed of the random generator, a random one if absent
+    pub seed: Option<u64>,
+}
+
+impl<'a> Default for SnowballOptions<'a> {
+    fn default() -> Self {
+        SnowballOptions {
+            waves: 2,
+            per_vertex: 10,
+            label: None,
+            at: None,
+            seed: None,
+        }
+    }
+}
+
+/// random walks, vertexes given by their positions in `vertexes`
+#[pyclass(module = "p3m")]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct Walks {
+    pub vertexes: Vec<ObjectId>,
+    /// a walk stops early at a vertex without outgoing edge
+    #[pyo3(get)]
+    pub walks: Vec<Vec<u32>>,
+}
+
+/// a sample of a graph, edges given by the positions of their endpoints in
+/// `vertexes`
+#[pyclass(module = "p3m")]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct Sample {
+    /// in the order they have been reached, the seed vertex first
+    pub vertexes: Vec<ObjectId>,
+    /// `(source, target)`
+    #[pyo3(get)]
+    pub edges: Vec<(u32, u32)>,
+    /// of each edge, 1.0 if absent
+    #[pyo3(get)]
+    pub weights: Vec<f64>,
+}
+
+impl Sample {
+    /// duplicated vertexes are kept once, edges out of `vertexes` are ignored
+    pub fn new(vertexes: &[ObjectId], edges: &[Edge]) -> Self {
+        let mut index = HashMap::new();
+        let mut ids = Vec::new();
+        for id in vertexes {
+            index.entry(*id).or_insert_with(|| {
+                ids.push(*id);
+                ids.len() as u32 - 1
+            });
+        }
+
+        let mut res = Sample {
+            vertexes: ids,
+            ..Default::default()
+        };
+        for e in edges {
+            if let (Some(s), Some(t)) = (index.get(&e.source), index.get(&e.target)) {
+                res.edges.push((*s, *t));
+                res.weights.push(e.weight.unwrap_or(1.0));
+            }
+        }
+
+        res
+    }
+}
+
+/// a random generator, seeded if `seed` is given
+pub fn rng(seed: Option<u64>) -> StdRng {
+    match seed {
+        Some(s) => StdRng::seed_from_u64(s),
+        None => StdRng::from_entropy(),
+    }
+}
+
+// an index picked at random, in proportion to `weights`; `None` if none of
+// them is positive
+fn pick(weights: &[f64], rng: &mut StdRng) -> Option<usize> {
+    let total: f64 = weights.iter().map(|w| w.max(0.0)).sum();
+    if total <= 0.0 {
+        return None;
+    }
+
+    let mut r = rng.gen::<f64>() * total;
+    let mut last = None;
+    for (i, w) in weights.iter().enumerate() {
+        if *w <= 0.0 {
+            continue;
+        }
+        if r < *w {
+            return Some(i);
+        }
+        r -= w;
+        last = Some(i);
+    }
+
+    // rounding errors
+    last
+}
+
+/// `options.walks_per_vertex` walks from each vertex, following outgoing
+/// edges. Vertexes which only appear in edges are also taken into account.
+pub fn random_walks(vertexes: &[ObjectId], edges: &[Edge], options: WalkOptions) -> Walks {
+    let adj = Adjacency::new(vertexes, edges);
+    let mut rng = rng(options.seed);
+
+    // targets of each vertex, to tell how far a step goes from the previous one
+    let targets = if options.biased() {
+        adj.out
+            .iter()
+            .map(|out| out.iter().map(|(t, _)| *t).collect::<HashSet<_>>())
+            .collect()
+    } else {
+        Vec::new()
+    };
+
+    let mut starts = (0..adj.len()).collect::<Vec<_>>();
+    let mut walks = Vec::with_capacity(adj.len() * options.walks_per_vertex);
+    let mut weights = Vec::new();
+    for _ in 0..options.walks_per_vertex {
+        // each round visits vertexes in a new order
+        starts.shuffle(&mut rng);
+        for &start in &starts {
+            let mut walk = vec![start];
+            while walk.len() < options.length {
+                let v = walk[walk.len() - 1];
+                let prev = walk.len().checked_sub(2).map(|i| walk[i]);
+
+                weights.clear();
+                weights.extend(adj.out[v].iter().map(|&(x, w)| {
+                    let w = if options.weighted { w } else { 1.0 };
+                    match prev {
+                        Some(t) if options.biased() => {
+                            if x == t {
+                                w / options.p
+                            } else if targets[t].contains(&x) {
+                                w
+                            } else {
+                                w / options.q
+                            }
+                        }
+                        _ => w,
+                    }
+                }));
+
+                match pick(&weights, &mut rng) {
+                    Some(i) => walk.push(adj.out[v][i].0),
+                    None => break,
+                }
+            }
+            walks.push(walk.into_iter().map(|i| i as u32).collect());
+        }
+    }
+
+    Walks {
+        vertexes: adj.ids,
+        walks,
+    }
+}
+
+/// at most `k` edges per source, picked at random
+pub fn pick_per_source(mut edges: Vec<Edge>, k: usize, rng: &mut StdRng) -> Vec<Edge> {
+    // edges come from MongoDB in no particular order, sorted for a seed to
+    // give the same sample
+    edges.sort_by_key(|e| (e.source, e.id));
+
+    let mut res = Vec::new();
+    for group in edges.chunk_by(|a, b| a.source == b.source) {
+        res.extend(group.choose_multiple(rng, k).cloned());
+    }
+
+    res
+}
+
+/// `size` documents matched by `filter`, picked uniformly at random
+pub fn sample_pipeline(filter: Document, size: usize) -> Vec<Document> {
+    vec![
+        doc! {"$match": filter},
+        doc! {"$sample": {"size": size as i64}},
+    ]
+}
+
+#[cfg(test)]
+mod test_sampling {
+    use super::*;
+
+    fn edge(source: ObjectId, target: ObjectId, weight: Option<f64>) -> Edge {
+        Edge {
+            id: Some(ObjectId::new()),
+            source,
+            target,
+            weight,
+            label: None,
+            valid_from: None,
+            valid_to: None,
+        }
+    }
+
+    #[test]
+    fn test_random_walks() {
+        let (a, b, c) = (ObjectId::new(), ObjectId::new(), ObjectId::new());
+        // a -> b -> c, b -> a
+        let edges = vec![edge(a, b, None), edge(b, c, None), edge(b, a, None)];
+        let options = WalkOptions {
+            length: 5,
+            walks_per_vertex: 3,
+            seed: Some(42),
+            ..Default::default()
+        };
+
+        let walks = random_walks(&[a, b, c], &edges, options);
+        assert_eq!(walks.vertexes, vec![a, b, c]);
+        assert_eq!(walks.walks.len(), 9);
+        for walk in &walks.walks {
+            // c is a dead end
+            assert!(walk.len() == 5 || walk.last() == Some(&2));
+            for step in walk.windows(2) {
+                let (s, t) = (
+                    walks.vertexes[step[0] as usize],
+                    walks.vertexes[step[1] as usize],
+                );
+                assert!(edges.iter().any(|e| e.source == s && e.target == t));
+            }
+        }
+
+        // reproducible given a seed
+        assert_eq!(random_walks(&[a, b, c], &edges, options), walks);
+    }
+
+    #[test]
+    fn test_biased_walks() {
+        let (a, b, c) = (ObjectId::new(), ObjectId::new(), ObjectId::new());
+        // a <-> b, b -> c weightless
+        let edges = vec![edge(a, b, None), edge(b, a, None), edge(b, c, Some(0.0))];
+
+        // weighted: no step to c
+        let options = WalkOptions {
+            length: 10,
+            weighted: true,
+            seed: Some(1),
+            ..Default::default()
+        };
+        let walks = random_walks(&[a, b, c], &edges, options);
+        assert!(walks
+            .walks
+            .iter()
+            .filter(|w| w[0] != 2)
+            .all(|w| w.len() == 10 && !w.contains(&2)));
+
+        // a high return parameter: from d to e, back to d is unlikely
+        let (d, e) = (ObjectId::new(), ObjectId::new());
+        let edges = vec![edge(d, e, None), edge(e, d, None), edge(e, c, None)];
+        let options = WalkOptions {
+            length: 3,
+            walks_per_vertex: 200,
+            p: 1e6,
+            seed: Some(7),
+            ..Default::default()
+        };
+        let walks = random_walks(&[d], &edges, options);
+        let back = walks
+            .walks
+            .iter()
+            .filter(|w| w[0] == 0 && w[2] == 0)
+            .count();
+        assert_eq!(back, 0);
+    }
+
+    #[test]
+    fn test_options() {
+        assert!(WalkOptions::default().check().is_ok());
+        let options = WalkOptions {
+            q: 0.0,
+            ..Default::default()
+        };
+        assert!(options.check().is_err());
+    }
+
+    #[test]
+    fn test_pick_per_source() {
+        let (a, b) = (ObjectId::new(), ObjectId::new());
+        let edges = (0..5)
+            .map(|_| edge(a, ObjectId::new(), None))
+            .chain([edge(b, a, None)])
+            .collect::<Vec<_>>();
+
+        let picked = pick_per_source(edges.clone(), 2, &mut rng(Some(3)));
+        assert_eq!(picked.iter().filter(|e| e.source == a).count(), 2);
+        assert_eq!(picked.iter().filter(|e| e.source == b).count(), 1);
+
+        let mut shuffled = edges;
+        shuffled.reverse();
+        assert_eq!(pick_per_source(shuffled, 2, &mut rng(Some(3))), picked);
+    }
+
+    #[test]
+    fn test_sample() {
+        let (a, b, c) = (ObjectId::new(), ObjectId::new(), ObjectId::new());
+        let edges = vec![edge(a, b, Some(2.0)), edge(b, c, None)];
+        let sample = Sample::new(&[a, b, a], &edges);
+
+        assert_eq!(sample.vertexes, vec![a, b]);
+        assert_eq!(sample.edges, vec![(0, 1)]);
+        assert_eq!(sample.weights, vec![2.0]);
+    }
+}
@@ -0,0 +1,1016 @@
+//! Store
+//!
+//! `GraphStore` is the core of a graph database: CRUD of vertexes & edges and
+//! traversal. It is implemented by `GraphService` (MongoDB), and by
+//! `MemoryStore`, an in-process stand-in which keeps everything in memory,
+//! mimicking what the Mongo pipelines do. The latter is meant for tests and
+//! for tools which shall run without a database.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::Mutex;
+
+use async_trait::async_trait;
+use mongodb::bson::oid::ObjectId;
+use mongodb::bson::DateTime;
+
+use crate::model::{
+    check_depth, check_validity, Edge, EdgeDto, FindEdgeByVertexDto, GraphStats, Vertex, VertexDto,
+};
+use crate::service::GraphService;
+use crate::{Pyo3MongoError, Pyo3MongoResult};
+
+/// core operations of a graph database, see `GraphService` for details
+#[async_trait]
+pub trait GraphStore: Send + Sync {
+    async fn truncate_all(&self) -> Pyo3MongoResult<()>;
+
+    async fn create_vertex<'a>(&self, dto: VertexDto<'a>) -> Pyo3MongoResult<Vertex>;
+
+    async fn get_vertex(&self, id: ObjectId) -> Pyo3MongoResult<Vertex>;
+
+    async fn get_vertexes(&self, ids: Vec<ObjectId>) -> Pyo3MongoResult<Vec<Vertex>>;
+
+    async fn get_all_vertexes(&self) -> Pyo3MongoResult<Vec<Vertex>>;
+
+    /// the first vertex named `name`
+    async fn get_vertex_by_name(&self, name: &str) -> Pyo3MongoResult<Vertex>;
+
+    /// the vertex named after `dto`, created if there is none
+    async fn get_or_create_vertex<'a>(&self, dto: VertexDto<'a>) -> Pyo3MongoResult<Vertex>;
+
+    /// returns the vertex before update
+    async fn update_vertex<'a>(&self, id: ObjectId, dto: VertexDto<'a>) -> Pyo3MongoResult<Vertex>;
+
+    /// related edges are deleted as well
+    async fn delete_vertex(&self, id: ObjectId) -> Pyo3MongoResult<()>;
+
+    async fn create_edge<'a>(&self, dto: EdgeDto<'a>) -> Pyo3MongoResult<Edge>;
+
+    async fn get_edge(&self, id: ObjectId) -> Pyo3MongoResult<Edge>;
+
+    async fn get_edges(&self, ids: Vec<ObjectId>) -> Pyo3MongoResult<Vec<Edge>>;
+
+    async fn get_all_edges(&self) -> Pyo3MongoResult<Vec<Edge>>;
+
+    /// returns the edge before update
+    async fn update_edge<'a>(&self, id: ObjectId, dto: EdgeDto<'a>) -> Pyo3MongoResult<Edge>;
+
+    async fn delete_edge(&self, id: ObjectId) -> Pyo3MongoResult<()>;
+
+    async fn delete_edges(&self, ids: Vec<ObjectId>) -> Pyo3MongoResult<()>;
+
+    async fn get_edges_by_vertex(
+        &self,
+        find_dto: FindEdgeByVertexDto,
+    ) -> Pyo3MongoResult<Vec<Edge>>;
+
+    async fn get_edges_from_vertex_by_label(
+        &self,
+        vertex_id: ObjectId,
+        label: Option<&str>,
+        depth: Option<i32>,
+        at: Option<DateTime>,
+    ) -> Pyo3MongoResult<Vec<Edge>>;
+
+    /// the same as `get_edges_from_vertex_by_label`, from several vertexes at
+    /// once, each edge being returned once
+    async fn get_edges_from_vertexes_by_label(
+        &self,
+        vertex_ids: Vec<ObjectId>,
+        label: Option<&str>,
+        depth: Option<i32>,
+        at: Option<DateTime>,
+    ) -> Pyo3MongoResult<Vec<Edge>>;
+
+    async fn get_graph_from_vertex_by_label(
+        &self,
+        vertex_id: ObjectId,
+        label: Option<&str>,
+        depth: Option<i32>,
+        at: Option<DateTime>,
+    ) -> Pyo3MongoResult<(Vec<Edge>, Vec<Vertex>)> {
+        let edges = self
+            .get_edges_from_vertex_by_label(vertex_id, label, depth, at)
+            .await?;
+
+        let target_ids = edges.iter().map(|e| e.target).collect::<Vec<_>>();
+        let vertexes = self.get_vertexes(target_ids).await?;
+
+        Ok((edges, vertexes))
+    }
+
+    /// insert vertexes & edges in bulk, edges may refer to vertexes of the batch
+    async fn insert_graph(
+        &self,
+        vertexes: Vec<Vertex>,
+        edges: Vec<Edge>,
+    ) -> Pyo3MongoResult<(Vec<Edge>, Vec<Vertex>)>;
+
+    async fn stats(&self) -> Pyo3MongoResult<GraphStats>;
+}
+
+#[async_trait]
+impl GraphStore for GraphService {
+    async fn truncate_all(&self) -> Pyo3MongoResult<()> {
+        GraphService::truncate_all(self).await
+    }
+
+    async fn create_vertex<'a>(&self, dto: VertexDto<'a>) -> Pyo3MongoResult<Vertex> {
+        GraphService::create_vertex(self, dto).await
+    }
+
+    async fn get_vertex(&self, id: ObjectId) -> Pyo3MongoResult<Vertex> {
+        GraphService::get_vertex(self, id).await
+    }
+
+    async fn get_vertexes(&self, ids: Vec<ObjectId>) -> Pyo3MongoResult<Vec<Vertex>> {
+        GraphService::get_vertexes(self, ids).await
+    }
+
+    async fn get_all_vertexes(&self) -> Pyo3MongoResult<Vec<Vertex>> {
+        GraphService::get_all_vertexes(self).await
+    }
+
+    async fn get_vertex_by_name(&self, name: &str) -> Pyo3MongoResult<Vertex> {
+        GraphService::get_vertex_by_name(self, name).await
+    }
+
+    async fn get_or_create_vertex<'a>(&self, dto: VertexDto<'a>) -> Pyo3MongoResult<Vertex> {
+        GraphService::get_or_create_vertex(self, dto).await
+    }
+
+    async fn update_vertex<'a>(&self, id: ObjectId, dto: VertexDto<'a>) -> Pyo3MongoResult<Vertex> {
+        GraphService::update_vertex(self, id, dto).await
+    }
+
+    async fn delete_vertex(&self, id: ObjectId) -> Pyo3MongoResult<()> {
+        GraphService::delete_vertex(self, id).await
+    }
+
+    async fn create_edge<'a>(&self, dto: EdgeDto<'a>) -> Pyo3MongoResult<Edge> {
+        GraphService::create_edge(self, dto).await
+    }
+
+    async fn get_edge(&self, id: ObjectId) -> Pyo3MongoResult<Edge> {
+        GraphService::get_edge(self, id).await
+    }
+
+    async fn get_edges(&self, ids: Vec<ObjectId>) -> Pyo3MongoResult<Vec<Edge>> {
+        GraphService::get_edges(self, ids).await
+    }
+
+    async fn get_all_edges(&self) -> Pyo3MongoResult<Vec<Edge>> {
+        GraphService::get_all_edges(self).await
+    }
+
+    async fn update_edge<'a>(&self, id: ObjectId, dto: EdgeDto<'a>) -> Pyo3MongoResult<Edge> {
+        GraphService::update_edge(self, id, dto).await
+    }
+
+    async fn delete_edge(&self, id: ObjectId) -> Pyo3MongoResult<()> {
+        GraphService::delete_edge(self, id).await
+    }
+
+    async fn delete_edges(&self, ids: Vec<ObjectId>) -> Pyo3MongoResult<()> {
+        GraphService::delete_edges(self, ids).await
+    }
+
+    async fn get_edges_by_vertex(
+        &self,
+        find_dto: FindEdgeByVertexDto,
+    ) -> Pyo3MongoResult<Vec<Edge>> {
+        GraphService::get_edges_by_vertex(self, find_dto).await
+    }
+
+    async fn get_edges_from_vertex_by_label(
+        &self,
+        vertex_id: ObjectId,
+        label: Option<&str>,
+        depth: Option<i32>,
+        at: Option<DateTime>,
+    ) -> Pyo3MongoResult<Vec<Edge>> {
+        GraphService::get_edges_from_vertex_by_label(self, vertex_id, label, depth, at).await
+    }
+
+    async fn get_edges_from_vertexes_by_label(
+        &self,
+        vertex_ids: Vec<ObjectId>,
+        label: Option<&str>,
+        depth: Option<i32>,
+        at: Option<DateTime>,
+    ) -> Pyo3MongoResult<Vec<Edge>> {
+        GraphService::get_edges_from_vertexes_by_label(self, vertex_ids, label, depth, at).await
+    }
+
+    async fn get_graph_from_vertex_by_label(
+        &self,
+        vertex_id: ObjectId,
+        label: Option<&str>,
+        depth: Option<i32>,
+        at: Option<DateTime>,
+    ) -> Pyo3MongoResult<(Vec<Edge>, Vec<Vertex>)> {
+        GraphService::get_graph_from_vertex_by_label(self, vertex_id, label, depth, at).await
+    }
+
+    async fn insert_graph(
+        &self,
+        vertexes: Vec<Vertex>,
+        edges: Vec<Edge>,
+    ) -> Pyo3MongoResult<(Vec<Edge>, Vec<Vertex>)> {
+        GraphService::insert_graph(self, vertexes, edges).await
+    }
+
+    async fn stats(&self) -> Pyo3MongoResult<GraphStats> {
+        GraphService::stats(self).await
+    }
+}
+
+#[derive(Default)]
+struct Collections {
+    // ObjectIds are increasing, so that a `BTreeMap` keeps the insertion order,
+    // like a Mongo collection's natural order
+    vertexes: BTreeMap<ObjectId, Vertex>,
+    edges: BTreeMap<ObjectId, Edge>,
+}
+
+/// in-process stand-in of `GraphService`
+#[derive(Default)]
+pub struct MemoryStore {
+    inner: Mutex<Collections>,
+}
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn with<T>(&self, f: impl FnOnce(&mut Collections) -> T) -> T {
+        f(&mut self.inner.lock().unwrap())
+    }
+}
+
+impl Collections {
+    fn check_edge_legitimacy(&self, dto: &EdgeDto) -> Pyo3MongoResult<()> {
+        check_validity(dto.valid_from, dto.valid_to)?;
+
+        if self.vertexes.contains_key(&dto.source) && self.vertexes.contains_key(&dto.target) {
+            Ok(())
+        } else {
+            Err(Pyo3MongoError::VertexNotFound)
+        }
+    }
+}
+
+#[async_trait]
+impl GraphStore for MemoryStore {
+    async fn truncate_all(&self) -> Pyo3MongoResult<()> {
+        self.with(|c| *c = Collections::default());
+        Ok(())
+    }
+
+    async fn create_vertex<'a>(&self, dto: VertexDto<'a>) -> Pyo3MongoResult<Vertex> {
+        let mut vertex = Vertex::from(dto);
+        let id = ObjectId::new();
+        vertex.id = Some(id);
+
+        self.with(|c| c.vertexes.insert(id, vertex.clone()));
+        Ok(vertex)
+    }
+
+    async fn get_vertex(&self, id: ObjectId) -> Pyo3MongoResult<Vertex> {
+        self.with(|c| c.vertexes.get(&id).cloned())
+            .ok_or(Pyo3MongoError::VertexNotFound)
+    }
+
+    async fn get_vertexes(&self, ids: Vec<ObjectId>) -> Pyo3MongoResult<Vec<Vertex>> {
+        let ids = ids.into_iter().collect::<HashSet<_>>();
+        Ok(self.with(|c| {
+            c.vertexes
+                .values()
+                .filter(|v| ids.contains(&v.id.unwrap()))
+                .cloned()
+                .collect()
+        }))
+    }
+
+    async fn get_all_vertexes(&self) -> Pyo3MongoResult<Vec<Vertex>> {
+        Ok(self.with(|c| c.vertexes.values().cloned().collect()))
+    }
+
+    async fn get_vertex_by_name(&self, name: &str) -> Pyo3MongoResult<Vertex> {
+        self.with(|c| c.vertexes.values().find(|v| v.name == name).cloned())
+            .ok_or(Pyo3MongoError::VertexNotFound)
+    }
+
+    async fn get_or_create_vertex<'a>(&self, dto: VertexDto<'a>) -> Pyo3MongoResult<Vertex> {
+        // under one lock, so that a name is never created twice
+        Ok(self.with(|c| {
+            if let Some(v) = c.vertexes.values().find(|v| v.name == dto.name) {
+                return v.clone();
+            }
+
+            let id = ObjectId::new();
+            let vertex = Vertex {
+                id: Some(id),
+                ..Vertex::from(dto)
+            };
+            c.vertexes.insert(id, vertex.clone());
+            vertex
+        }))
+    }
+
+    async fn update_vertex<'a>(&self, id: ObjectId, dto: VertexDto<'a>) -> Pyo3MongoResult<Vertex> {
+        self.with(|c| {
+            let vertex = c
+                .vertexes
+                .get_mut(&id)
+                .ok_or(Pyo3MongoError::VertexNotFound)?;
+            let before = vertex.clone();
+            // same as `$set`, properties are untouched
+            vertex.name = dto.name.to_owned();
+            Ok(before)
+        })
+    }
+
+    async fn delete_vertex(&self, id: ObjectId) -> Pyo3MongoResult<()> {
+        self.with(|c| {
+            c.vertexes
+                .remove(&id)
+                .ok_or(Pyo3MongoError::VertexNotFound)?;
+            c.edges.retain(|_, e| e.source != id && e.target != id);
+            Ok(())
+        })
+    }
+
+    async fn create_edge<'a>(&self, dto: EdgeDto<'a>) -> Pyo3MongoResult<Edge> {
+        self.with(|c| {
+            c.check_edge_legitimacy(&dto)?;
+
+            let mut edge = Edge::from(dto);
+            let id = ObjectId::new();
+            edge.id = Some(id);
+            c.edges.insert(id, edge.clone());
+            Ok(edge)
+        })
+    }
+
+    async fn get_edge(&self, id: ObjectId) -> Pyo3MongoResult<Edge> {
+        self.with(|c| c.edges.get(&id).cloned())
+            .ok_or(Pyo3MongoError::EdgeNotFound)
+    }
+
+    async fn get_edges(&self, ids: Vec<ObjectId>) -> Pyo3MongoResult<Vec<Edge>> {
+        let ids = ids.into_iter().collect::<HashSet<_>>();
+        Ok(self.with(|c| {
+            c.edges
+                .values()
+                .filter(|e| ids.contains(&e.id.unwrap()))
+                .cloned()
+                .collect()
+        }))
+    }
+
+    async fn get_all_edges(&self) -> Pyo3MongoResult<Vec<Edge>> {
+        Ok(self.with(|c| c.edges.values().cloned().collect()))
+    }
+
+    async fn update_edge<'a>(&self, id: ObjectId, dto: EdgeDto<'a>) -> Pyo3MongoResult<Edge> {
+        self.with(|c| {
+            c.check_edge_legitimacy(&dto)?;
+
+            let edge = c.edges.get_mut(&id).ok_or(Pyo3MongoError::EdgeNotFound)?;
+            let before = edge.clone();
+            *edge = Edge {
+                id: Some(id),
+                ..Edge::from(dto)
+            };
+            Ok(before)
+        })
+    }
+
+    async fn delete_edge(&self, id: ObjectId) -> Pyo3MongoResult<()> {
+        self.with(|c| c.edges.remove(&id))
+            .map(|_| ())
+            .ok_or(Pyo3MongoError::EdgeNotFound)
+    }
+
+    async fn delete_edges(&self, ids: Vec<ObjectId>) -> Pyo3MongoResult<()> {
+        let deleted = self.with(|c| ids.iter().filter(|id| c.edges.remove(id).is_some()).count());
+
+        if deleted == 0 {
+            return Err(Pyo3MongoError::EdgeNotFound);
+        }
+
+        Ok(())
+    }
+
+    async fn get_edges_by_vertex(
+        &self,
+        find_dto: FindEdgeByVertexDto,
+    ) -> Pyo3MongoResult<Vec<Edge>> {
+        let id = find_dto.id();
+        let related = |e: &Edge| match find_dto {
+            FindEdgeByVertexDto::Source(_) => e.source == id,
+            FindEdgeByVertexDto::Target(_) => e.target == id,
+            FindEdgeByVertexDto::Bidirectional(_) => e.source == id || e.target == id,
+        };
+
+        Ok(self.with(|c| {
+            // like `$lookup`, nothing if the vertex itself does not exist
+            if !c.vertexes.contains_key(&id) {
+                return vec![];
+            }
+            c.edges.values().filter(|e| related(e)).cloned().collect()
+        }))
+    }
+
+    async fn get_edges_from_vertex_by_label(
+        &self,
+        vertex_id: ObjectId,
+        label: Option<&str>,
+        depth: Option<i32>,
+        at: Option<DateTime>,
+    ) -> Pyo3MongoResult<Vec<Edge>> {
+        self.get_edges_from_vertexes_by_label(vec![vertex_id], label, depth, at)
+            .await
+    }
+
+    async fn get_edges_from_vertexes_by_label(
+        &self,
+        vertex_ids: Vec<ObjectId>,
+        label: Option<&str>,
+        depth: Option<i32>,
+        at: Option<DateTime>,
+    ) -> Pyo3MongoResult<Vec<Edge>> {
+        check_depth(depth)?;
+
+        Ok(self.with(|c| {
+            // breadth-first, the same as `$graphLookup`: edges whose source is
+            // one of the (existing) vertexes are of depth 0, and each edge is
+            // only visited once
+            let mut res = Vec::new();
+            let mut visited = HashSet::new();
+            let mut frontier = vertex_ids
+                .into_iter()
+                .filter(|id| c.vertexes.contains_key(id))
+                .collect::<HashSet<_>>();
+            let mut d = 0;
+            while !frontier.is_empty() && depth.is_none_or(|n| d <= n) {
+                let found = c
+                    .edges
+                    .values()
+                    .filter(|e| frontier.contains(&e.source))
+                    .filter(|e| label.is_none_or(|l| e.label.as_deref() == Some(l)))
+                    .filter(|e| at.is_none_or(|at| e.is_valid_at(at)))
+                    .filter(|e| visited.insert(e.id.unwrap()))
+                    .cloned()
+                    .collect::<Vec<_>>();
+
+                frontier = found.iter().map(|e| e.target).collect();
+                res.extend(found);
+                d += 1;
+            }
+
+            res
+        }))
+    }
+
+    async fn insert_graph(
+        &self,
+        mut vertexes: Vec<Vertex>,
+        mut edges: Vec<Edge>,
+    ) -> Pyo3MongoResult<(Vec<Edge>, Vec<Vertex>)> {
+        for v in vertexes.iter_mut() {
+            v.id.get_or_insert_with(ObjectId::new);
+        }
+        for e in edges.iter_mut() {
+            check_validity(e.valid_from, e.valid_to)?;
+            e.id.get_or_insert_with(ObjectId::new);
+        }
+
+        self.with(|c| {
+            // as `insert_many` does, ids must be new, within the batch too
+            let (mut vertex_ids, mut edge_ids) = (HashSet::new(), HashSet::new());
+            let duplicate = vertexes
+                .iter()
+                .filter_map(|v| v.id)
+                .find(|id| c.vertexes.contains_key(id) || !vertex_ids.insert(*id))
+                .or_else(|| {
+                    edges
+                        .iter()
+                        .filter_map(|e| e.id)
+                        .find(|id| c.edges.contains_key(id) || !edge_ids.insert(*id))
+                });
+            if let Some(id) = duplicate {
+                return Err(Pyo3MongoError::Conflict(format!("duplicate id {}", id)));
+            }
+
+            let batch = vertexes.iter().filter_map(|v| v.id).collect::<HashSet<_>>();
+            let exists = |id| batch.contains(id) || c.vertexes.contains_key(id);
+            if !edges.iter().all(|e| exists(&e.source) && exists(&e.target)) {
+                return Err(Pyo3MongoError::VertexNotFound);
+            }
+
+            for v in &vertexes {
+                c.vertexes.insert(v.id.unwrap(), v.clone());
+            }
+            for e in &edges {
+                c.edges.insert(e.id.unwrap(), e.clone());
+            }
+            Ok(())
+        })?;
+
+        Ok((edges, vertexes))
+    }
+
+    /// the same as `GraphService::stats`, without indexes
+    async fn stats(&self) -> Pyo3MongoResult<GraphStats> {
+        Ok(self.with(|c| {
+            let mut labels = BTreeMap::new();
+            let mut degrees = HashMap::<ObjectId, u64>::new();
+            let mut dangling_edge_count = 0;
+            for e in c.edges.values() {
+                if let Some(l) = &e.label {
+                    *labels.entry(l.clone()).or_default() += 1;
+                }
+                for id in [e.source, e.target] {
+                    *degrees.entry(id).or_default() += 1;
+                }
+                if !c.vertexes.contains_key(&e.source) || !c.vertexes.contains_key(&e.target) {
+                    dangling_edge_count += 1;
+                }
+            }
+
+            let vertex_count = c.vertexes.len() as u64;
+            let edge_count = c.edges.len() as u64;
+            let average_degree = if vertex_count == 0 {
+                0.0
+            } else {
+                2.0 * edge_count as f64 / vertex_count as f64
+            };
+            let orphan_vertex_count = c
+                .vertexes
+                .keys()
+                .filter(|id| !degrees.contains_key(id))
+                .count() as u64;
+
+            GraphStats {
+                vertex_count,
+                edge_count,
+                unlabeled_edge_count: edge_count - labels.values().sum::<u64>(),
+                labels,
+                average_degree,
+                orphan_vertex_count,
+                dangling_edge_count,
+                indexes: vec![],
+            }
+        }))
+    }
+}
+
+#[cfg(test)]
+mod test_store {
+    use super::*;
+    use crate::harness::store_suite;
+
+    const LABEL: &str = "test-label";
+
+    store_suite!(
+        vertex_crud,
+        vertex_by_name,
+        truncate_all,
+        edge_circuit,
+        edge_crud,
+        missing_vertex,
+        edge_validity,
+        depth,
+        insert_graph,
+        stats,
+    );
+
+    async fn vertex_crud(gs: &impl GraphStore) {
+        let create = gs.create_vertex(VertexDto::new("node-1")).await.unwrap();
+
+        let id = create.id.unwrap();
+        let get = gs.get_vertex(id).await.unwrap();
+        assert_eq!(create, get);
+
+        let update = gs
+            .update_vertex(id, VertexDto::new("node-2"))
+            .await
+            .unwrap();
+        let get = gs.get_vertex(id).await.unwrap();
+
+        // name has been changed
+        assert_ne!(update, get);
+
+        let delete = gs.delete_vertex(id).await;
+        assert!(delete.is_ok());
+    }
+
+    async fn vertex_by_name(gs: &impl GraphStore) {
+        assert!(gs.get_vertex_by_name("named-1").await.is_err());
+
+        let create = gs
+            .get_or_create_vertex(VertexDto::new("named-1"))
+            .await
+            .unwrap();
+        let get = gs.get_vertex_by_name("named-1").await.unwrap();
+        assert_eq!(create, get);
+
+        // no second vertex of the same name
+        let again = gs
+            .get_or_create_vertex(VertexDto::new("named-1"))
+            .await
+            .unwrap();
+        assert_eq!(create.id, again.id);
+        assert_eq!(gs.get_all_vertexes().await.unwrap().len(), 1);
+    }
+
+    async fn truncate_all(gs: &impl GraphStore) {
+        let res = gs.truncate_all().await;
+        assert!(res.is_ok());
+    }
+
+    async fn edge_circuit(gs: &impl GraphStore) {
+        let node1 = gs.create_vertex(VertexDto::new("node-1")).await.unwrap();
+        let node2 = gs.create_vertex(VertexDto::new("node-2")).await.unwrap();
+        let node3 = gs.create_vertex(VertexDto::new("node-3")).await.unwrap();
+
+        // node1 -> node2
+        gs.create_edge(EdgeDto::new(
+            node1.id.unwrap(),
+            node2.id.unwrap(),
+            Some(1.0),
+            Some(LABEL),
+        ))
+        .await
+        .unwrap();
+
+        // node2 -> node3
+        gs.create_edge(EdgeDto::new(
+            node2.id.unwrap(),
+            node3.id.unwrap(),
+            Some(2.0),
+            Some(LABEL),
+        ))
+        .await
+        .unwrap();
+
+        // node3 -> node1
+        gs.create_edge(EdgeDto::new(
+            node3.id.unwrap(),
+            node1.id.unwrap(),
+            Some(3.0),
+            Some(LABEL),
+        ))
+        .await
+        .unwrap();
+
+        let (edges, vertexes) = gs
+            .get_graph_from_vertex_by_label(node1.id.unwrap(), None, None, None)
+            .await
+            .unwrap();
+
+        assert_eq!(edges.len(), 3);
+        assert_eq!(vertexes.len(), 3);
+    }
+
+    async fn edge_crud(gs: &impl GraphStore) {
+        let node1 = gs.create_vertex(VertexDto::new("node-1")).await.unwrap();
+        let node2 = gs.create_vertex(VertexDto::new("node-2")).await.unwrap();
+        let node3 = gs.create_vertex(VertexDto::new("node-3")).await.unwrap();
+        let node4 = gs.create_vertex(VertexDto::new("node-4")).await.unwrap();
+        let node5 = gs.create_vertex(VertexDto::new("node-5")).await.unwrap();
+        let node6 = gs.create_vertex(VertexDto::new("node-6")).await.unwrap();
+        let node7 = gs.create_vertex(VertexDto::new("node-7")).await.unwrap();
+        let node8 = gs.create_vertex(VertexDto::new("node-8")).await.unwrap();
+        let node9 = gs.create_vertex(VertexDto::new("node-9")).await.unwrap();
+
+        // node1 -> node2
+        gs.create_edge(EdgeDto::new(
+            node1.id.unwrap(),
+            node2.id.unwrap(),
+            Some(2.0),
+            Some(LABEL),
+        ))
+        .await
+        .unwrap();
+
+        // node1 -> node3
+        let edge2 = gs
+            .create_edge(EdgeDto::new(
+                node1.id.unwrap(),
+                node3.id.unwrap(),
+                Some(3.0),
+                Some(LABEL),
+            ))
+            .await
+            .unwrap();
+
+        // node2 -> node3
+        let edge2update = gs
+            .update_edge(
+                edge2.id.unwrap(),
+                EdgeDto::new(node2.id.unwrap(), node3.id.unwrap(), Some(3.0), Some(LABEL)),
+            )
+            .await;
+        assert!(edge2update.is_ok());
+
+        // this will return edge1 and edge2
+        let edges = gs
+            .get_edges_from_vertex_by_label(node1.id.unwrap(), Some(LABEL), None, None)
+            .await
+            .unwrap();
+        assert_eq!(edges.len(), 2);
+
+        // node1 -> node4
+        gs.create_edge(EdgeDto::new(
+            node1.id.unwrap(),
+            node4.id.unwrap(),
+            Some(4.0),
+            Some(LABEL),
+        ))
+        .await
+        .unwrap();
+
+        // node1 -> node5
+        gs.create_edge(EdgeDto::new(
+            node1.id.unwrap(),
+            node5.id.unwrap(),
+            Some(5.0),
+            Some(LABEL),
+        ))
+        .await
+        .unwrap();
+
+        // node5 -> node4
+        gs.create_edge(EdgeDto::new(
+            node5.id.unwrap(),
+            node4.id.unwrap(),
+            Some(2.1),
+            Some(LABEL),
+        ))
+        .await
+        .unwrap();
+
+        // node5 -> node6
+        gs.create_edge(EdgeDto::new(
+            node5.id.unwrap(),
+            node6.id.unwrap(),
+            Some(2.2),
+            Some(LABEL),
+        ))
+        .await
+        .unwrap();
+
+        // node7 -> node1
+        gs.create_edge(EdgeDto::new(
+            node7.id.unwrap(),
+            node1.id.unwrap(),
+            Some(4.3),
+            Some(LABEL),
+        ))
+        .await
+        .unwrap();
+
+        // node8 -> node7
+        gs.create_edge(EdgeDto::new(
+            node8.id.unwrap(),
+            node7.id.unwrap(),
+            Some(4.4),
+            Some(LABEL),
+        ))
+        .await
+        .unwrap();
+
+        // node8 -> node2
+        gs.create_edge(EdgeDto::new(
+            node8.id.unwrap(),
+            node2.id.unwrap(),
+            Some(4.5),
+            Some(LABEL),
+        ))
+        .await
+        .unwrap();
+
+        // node9 -> node3
+        gs.create_edge(EdgeDto::new(
+            node9.id.unwrap(),
+            node3.id.unwrap(),
+            Some(4.6),
+            Some(LABEL),
+        ))
+        .await
+        .unwrap();
+
+        /*
+        n8 -> n7 -> n1 -> n2 -> n3
+        n8 -> n2
+        n1 -> n4
+        n1 -> n5 -> n4
+        n5 -> n6
+        n9 -> n3
+        */
+        let (edges, vertexes) = gs
+            .get_graph_from_vertex_by_label(node1.id.unwrap(), None, None, None)
+            .await
+            .unwrap();
+        assert_eq!(edges.len(), 6);
+        assert_eq!(vertexes.len(), 5);
+
+        // delete node2, related edges should be deleted: n8 -> n2, n1 -> n2, n2 -> n3
+        let delete_n2 = gs.delete_vertex(node2.id.unwrap()).await;
+        assert!(delete_n2.is_ok());
+
+        // node1 graph
+        let (edges, vertexes) = gs
+            .get_graph_from_vertex_by_label(node1.id.unwrap(), None, None, None)
+            .await
+            .unwrap();
+        assert_eq!(edges.len(), 4);
+        assert_eq!(vertexes.len(), 3);
+
+        // node8 graph
+        let (edges, vertexes) = gs
+            .get_graph_from_vertex_by_label(node8.id.unwrap(), None, None, None)
+            .await
+            .unwrap();
+        assert_eq!(edges.len(), 6);
+        assert_eq!(vertexes.len(), 5);
+
+        // delete node1, related edges should be deleted: n7 -> n1, n1 -> n4, n1 -> n5
+        let delete_n1 = gs.delete_vertex(node1.id.unwrap()).await;
+        assert!(delete_n1.is_ok());
+
+        // node8 graph
+        let (edges, vertexes) = gs
+            .get_graph_from_vertex_by_label(node8.id.unwrap(), None, None, None)
+            .await
+            .unwrap();
+        assert_eq!(edges.len(), 1);
+        assert_eq!(vertexes.len(), 1);
+    }
+
+    async fn missing_vertex(gs: &impl GraphStore) {
+        let node1 = gs.create_vertex(VertexDto::new("node-1")).await.unwrap();
+        let id1 = node1.id.unwrap();
+
+        // an edge cannot point to a vertex which does not exist
+        let res = gs
+            .create_edge(EdgeDto::new(id1, ObjectId::new(), None, Some(LABEL)))
+            .await;
+        assert!(res.is_err());
+
+        let missing = ObjectId::new();
+        assert!(gs.get_vertex(missing).await.is_err());
+        assert!(gs.delete_vertex(missing).await.is_err());
+        let edges = gs
+            .get_edges_by_vertex(FindEdgeByVertexDto::Bidirectional(missing))
+            .await
+            .unwrap();
+        assert!(edges.is_empty());
+    }
+
+    async fn edge_validity(gs: &impl GraphStore) {
+        let day = |d: i64| DateTime::from_millis(d * 86_400_000);
+
+        let node1 = gs.create_vertex(VertexDto::new("node-1")).await.unwrap();
+        let node2 = gs.create_vertex(VertexDto::new("node-2")).await.unwrap();
+        let node3 = gs.create_vertex(VertexDto::new("node-3")).await.unwrap();
+        let (id1, id2, id3) = (node1.id.unwrap(), node2.id.unwrap(), node3.id.unwrap());
+
+        // node1 -> node2 during [day 1, day 3), node2 -> node3 since day 2
+        gs.create_edge(
+            EdgeDto::new(id1, id2, None, Some(LABEL)).valid_between(Some(day(1)), Some(day(3))),
+        )
+        .await
+        .unwrap();
+        gs.create_edge(EdgeDto::new(id2, id3, None, Some(LABEL)).valid_between(Some(day(2)), None))
+            .await
+            .unwrap();
+
+        let count = |at| async move {
+            gs.get_edges_from_vertex_by_label(id1, Some(LABEL), None, at)
+                .await
+                .unwrap()
+                .len()
+        };
+        assert_eq!(count(None).await, 2);
+        assert_eq!(count(Some(day(0))).await, 0);
+        // node2 -> node3 is not valid yet
+        assert_eq!(count(Some(day(1))).await, 1);
+        assert_eq!(count(Some(day(2))).await, 2);
+        // upper bound is exclusive, node2 is no longer reachable
+        assert_eq!(count(Some(day(3))).await, 0);
+
+        let (edges, vertexes) = gs
+            .get_graph_from_vertex_by_label(id1, None, None, Some(day(2)))
+            .await
+            .unwrap();
+        assert_eq!((edges.len(), vertexes.len()), (2, 2));
+
+        // an empty interval
+        let res = gs
+            .create_edge(
+                EdgeDto::new(id1, id3, None, None).valid_between(Some(day(3)), Some(day(3))),
+            )
+            .await;
+        assert!(res.is_err());
+    }
+
+    async fn depth(gs: &impl GraphStore) {
+        let node1 = gs.create_vertex(VertexDto::new("node-1")).await.unwrap();
+        let node2 = gs.create_vertex(VertexDto::new("node-2")).await.unwrap();
+        let node3 = gs.create_vertex(VertexDto::new("node-3")).await.unwrap();
+        let (id1, id2, id3) = (node1.id.unwrap(), node2.id.unwrap(), node3.id.unwrap());
+
+        // node1 -> node2 -> node3
+        gs.create_edge(EdgeDto::new(id1, id2, None, Some(LABEL)))
+            .await
+            .unwrap();
+        gs.create_edge(EdgeDto::new(id2, id3, None, Some(LABEL)))
+            .await
+            .unwrap();
+
+        // depth 0: edges from the vertex itself only
+        let edges = gs
+            .get_edges_from_vertex_by_label(id1, None, Some(0), None)
+            .await
+            .unwrap();
+        assert_eq!(edges.len(), 1);
+
+        let res = gs
+            .get_edges_from_vertex_by_label(id1, None, Some(-1), None)
+            .await;
+        assert!(res.is_err());
+    }
+
+    async fn insert_graph(gs: &impl GraphStore) {
+        let existing = gs.create_vertex(VertexDto::new("node-0")).await.unwrap();
+        let vertex = |name: &str| Vertex {
+            id: None,
+            name: name.to_owned(),
+            properties: None,
+        };
+        let (edges, vertexes) = gs
+            .insert_graph(vec![vertex("node-1"), vertex("node-2")], vec![])
+            .await
+            .unwrap();
+        assert!(edges.is_empty());
+        let (id1, id2) = (vertexes[0].id.unwrap(), vertexes[1].id.unwrap());
+
+        // edges refer to the batch, and to existing vertexes
+        let edge = |source, target| Edge::from(EdgeDto::new(source, target, None, Some(LABEL)));
+        let (edges, _) = gs
+            .insert_graph(
+                vec![vertex("node-3")],
+                vec![edge(id1, id2), edge(existing.id.unwrap(), id1)],
+            )
+            .await
+            .unwrap();
+        assert_eq!(gs.get_edge(edges[1].id.unwrap()).await.unwrap(), edges[1]);
+        assert_eq!(gs.get_all_vertexes().await.unwrap().len(), 4);
+
+        let missing = gs
+            .insert_graph(vec![], vec![edge(id1, ObjectId::new())])
+            .await;
+        assert!(missing.is_err());
+        assert_eq!(gs.get_all_edges().await.unwrap().len(), 2);
+
+        // ids already taken are rejected, not overwritten
+        let taken = Vertex {
+            id: Some(id1),
+            ..vertex("node-4")
+        };
+        assert!(gs.insert_graph(vec![taken], vec![]).await.is_err());
+        assert_eq!(gs.get_vertex(id1).await.unwrap().name, "node-1");
+        let taken = Edge {
+            id: edges[0].id,
+            ..edge(id2, id1)
+        };
+        assert!(gs.insert_graph(vec![], vec![taken]).await.is_err());
+        assert_eq!(gs.get_edge(edges[0].id.unwrap()).await.unwrap(), edges[0]);
+        assert_eq!(gs.get_all_edges().await.unwrap().len(), 2);
+    }
+
+    async fn stats(gs: &impl GraphStore) {
+        let node1 = gs.create_vertex(VertexDto::new("node-1")).await.unwrap();
+        let node2 = gs.create_vertex(VertexDto::new("node-2")).await.unwrap();
+        gs.create_vertex(VertexDto::new("node-3")).await.unwrap();
+        let (id1, id2) = (node1.id.unwrap(), node2.id.unwrap());
+        gs.create_edge(EdgeDto::new(id1, id2, None, Some(LABEL)))
+            .await
+            .unwrap();
+        gs.create_edge(EdgeDto::new(id2, id1, None, None))
+            .await
+            .unwrap();
+
+        let stats = gs.stats().await.unwrap();
+        assert_eq!((stats.vertex_count, stats.edge_count), (3, 2));
+        assert_eq!(stats.labels.get(LABEL), Some(&1));
+        assert_eq!(stats.unlabeled_edge_count, 1);
+        assert_eq!(stats.orphan_vertex_count, 1);
+        assert_eq!(stats.dangling_edge_count, 0);
+    }
+}
@@ -0,0 +1,477 @@
+//! Telemetry
+//!
+//! Every `GraphService` operation runs within a `tracing` span named
+//! "operation" (category, operation, arguments, then result size, duration &
+//! error), and is counted in a Prometheus-style registry of the process:
+//!
+//! - `p3m_operations_total{category, operation, outcome}`, a counter
+//! - `p3m_operation_duration_seconds{category, operation}`, a histogram
+//! - `p3m_operation_result_size{category, operation}`, a histogram of the number
+//!   of items returned by successful operations
+//! - `p3m_operation_retries_total{category, operation}`, a counter of retries on
+//!   transient failures
+//!
+//! `render` exports the registry in the Prometheus text format. Spans are only
+//! seen once a subscriber is installed, e.g. by `init_tracing`.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::{Debug, Write};
+use std::future::Future;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use tracing::field::{display, Empty};
+use tracing::{Instrument, Span};
+
+use crate::diff::GraphDiff;
+use crate::model::{
+    Edge, GraphStats, HyperEdge, IntegrityReport, Neighborhood, ValidationReport, Vertex,
+};
+use crate::sampling::{Sample, Walks};
+use crate::weights::AdjacencyMatrix;
+use crate::Pyo3MongoResult;
+
+/// upper bounds of duration buckets, in seconds
+const DURATION_BUCKETS: &[f64] = &[0.001, 0.005, 0.01, 0.05, 0.1, 0.5, 1.0, 5.0];
+/// upper bounds of result size buckets
+const SIZE_BUCKETS: &[f64] = &[0.0, 1.0, 10.0, 100.0, 1000.0, 10000.0];
+
+/// number of items an operation returns, `1` for a single one
+pub trait ResultSize {
+    fn result_size(&self) -> usize;
+}
+
+impl ResultSize for () {
+    fn result_size(&self) -> usize {
+        0
+    }
+}
+
+macro_rules! single {
+    ($($t:ty),*) => {
+        $(
+            impl ResultSize for $t {
+                fn result_size(&self) -> usize {
+                    1
+                }
+            }
+        )*
+    };
+}
+
+single!(
+    Vertex,
+    Edge,
+    HyperEdge,
+    Duration,
+    GraphStats,
+    AdjacencyMatrix
+);
+
+impl<T> ResultSize for Vec<T> {
+    fn result_size(&self) -> usize {
+        self.len()
+    }
+}
+
+impl<K, V> ResultSize for HashMap<K, V> {
+    fn result_size(&self) -> usize {
+        self.len()
+    }
+}
+
+impl<A, B> ResultSize for (Vec<A>, Vec<B>) {
+    fn result_size(&self) -> usize {
+        self.0.len() + self.1.len()
+    }
+}
+
+impl ResultSize for Neighborhood {
+    fn result_size(&self) -> usize {
+        self.vertexes.len() + self.edges.len()
+    }
+}
+
+impl ResultSize for IntegrityReport {
+    fn result_size(&self) -> usize {
+        self.dangling_edges.len()
+            + self.duplicate_edges.iter().map(Vec::len).sum::<usize>()
+            + self.invalid_edges.len()
+            + self.invalid_vertexes.len()
+            + self.dangling_hyperedges.len()
+    }
+}
+
+impl ResultSize for GraphDiff {
+    fn result_size(&self) -> usize {
+        self.added_vertexes.len()
+            + self.removed_vertexes.len()
+            + self.changed_vertexes.len()
+            + self.added_edges.len()
+            + self.removed_edges.len()
+            + self.changed_edges.len()
+    }
+}
+
+impl ResultSize for Walks {
+    fn result_size(&self) -> usize {
+        self.walks.len()
+    }
+}
+
+impl ResultSize for Sample {
+    fn result_size(&self) -> usize {
+        self.vertexes.len() + self.edges.len()
+    }
+}
+
+impl ResultSize for ValidationReport {
+    fn result_size(&self) -> usize {
+        self.vertexes.len() + self.edges.len() + self.hyperedges.len()
+    }
+}
+
+/// a histogram of fixed buckets
+#[derive(Debug, Clone)]
+struct Histogram {
+    bounds: &'static [f64],
+    /// per bucket, not cumulative
+    counts: Vec<u64>,
+    sum: f64,
+    count: u64,
+}
+
+impl Histogram {
+    fn new(bounds: &'static [f64]) -> Self {
+        Histogram {
+            bounds,
+            counts: vec![0; bounds.len()],
+            sum: 0.0,
+            count: 0,
+        }
+    }
+
+    fn observe(&mut self, value: f64) {
+        if let Some(i) = self.bounds.iter().position(|b| value <= *b) {
+            self.counts[i] += 1;
+        }
+        self.sum += value;
+        self.count += 1;
+    }
+
+    fn render(&self, out: &mut String, name: &str, labels: &str) {
+        let mut cumulative = 0;
+        for (bound, n) in self.bounds.iter().zip(&self.counts) {
+            cumulative += n;
+            let _ = writeln!(
+                out,
+                "{}_bucket{{{},le=\"{}\"}} {}",
+                name, labels, bound, cumulative
+            );
+        }
+        let _ = writeln!(
+            out,
+            "{}_bucket{{{},le=\"+Inf\"}} {}",
+            name, labels, self.count
+        );
+        let _ = writeln!(out, "{}_sum{{{}}} {}", name, labels, self.sum);
+        let _ = writeln!(out, "{}_count{{{}}} {}", name, labels, self.count);
+    }
+}
+
+#[derive(Debug, Clone)]
+struct OperationMetrics {
+    ok: u64,
+    error: u64,
+    retries: u64,
+    duration: Histogram,
+    size: Histogram,
+}
+
+impl Default for OperationMetrics {
+    fn default() -> Self {
+        OperationMetrics {
+            ok: 0,
+            error: 0,
+            retries: 0,
+            duration: Histogram::new(DURATION_BUCKETS),
+            size: Histogram::new(SIZE_BUCKETS),
+        }
+    }
+}
+
+/// metrics of operations, by category & operation
+#[derive(Default)]
+pub struct Registry {
+    operations: Mutex<BTreeMap<(String, &'static str), OperationMetrics>>,
+}
+
+/// the registry of the process
+pub fn registry() -> &'static Registry {
+    static REGISTRY: OnceLock<Registry> = OnceLock::new();
+    REGISTRY.get_or_init(Registry::default)
+}
+
+/// `Content-Type` of `render`
+pub const CONTENT_TYPE: &str = "text/plain; version=0.0.4";
+
+/// the registry of the process, in the Prometheus text format
+pub fn render() -> String {
+    registry().render()
+}
+
+// label values are quoted, see the Prometheus text format
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+impl Registry {
+    /// `size` is the result size of a successful operation, `None` if it failed
+    pub fn observe(
+        &self,
+        category: &str,
+        operation: &'static str,
+        duration: Duration,
+        size: Option<usize>,
+    ) {
+        let mut operations = self.operations.lock().unwrap();
+        let metrics = operations
+            .entry((category.to_owned(), operation))
+            .or_default();
+
+        metrics.duration.observe(duration.as_secs_f64());
+        match size {
+            Some(n) => {
+                metrics.ok += 1;
+                metrics.size.observe(n as f64);
+            }
+            None => metrics.error += 1,
+        }
+    }
+
+    /// an operation is run again
+    pub fn retried(&self, category: &str, operation: &'static str) {
+        let mut operations = self.operations.lock().unwrap();
+        operations
+            .entry((category.to_owned(), operation))
+            .or_default()
+            .retries += 1;
+    }
+
+    /// retries of an operation
+    pub fn retries(&self, category: &str, operation: &str) -> u64 {
+        self.operations
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|((c, o), _)| c == category && *o == operation)
+            .map_or(0, |(_, m)| m.retries)
+    }
+
+    /// `(ok, error)` counts of an operation
+    pub fn count(&self, category: &str, operation: &str) -> (u64, u64) {
+        self.operations
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|((c, o), _)| c == category && *o == operation)
+            .map_or((0, 0), |(_, m)| (m.ok, m.error))
+    }
+
+    pub fn render(&self) -> String {
+        // a snapshot, so that the lock is not held while formatting
+        let operations = self.operations.lock().unwrap().clone();
+        let labels = |category: &str, operation: &str| {
+            format!(
+                "category=\"{}\",operation=\"{}\"",
+                escape(category),
+                escape(operation)
+            )
+        };
+
+        let mut out = String::new();
+        out.push_str("# HELP p3m_operations_total Graph operations, by outcome.\n");
+        out.push_str("# TYPE p3m_operations_total counter\n");
+        for ((category, operation), m) in &operations {
+            for (outcome, n) in [("ok", m.ok), ("error", m.error)] {
+                let _ = writeln!(
+                    out,
+                    "p3m_operations_total{{{},outcome=\"{}\"}} {}",
+                    labels(category, operation),
+                    outcome,
+                    n
+                );
+            }
+        }
+
+        out.push_str("# HELP p3m_operation_duration_seconds Duration of graph operations.\n");
+        out.push_str("# TYPE p3m_operation_duration_seconds histogram\n");
+        for ((category, operation), m) in &operations {
+            let name = "p3m_operation_duration_seconds";
+            m.duration
+                .render(&mut out, name, &labels(category, operation));
+        }
+
+        out.push_str(
+            "# HELP p3m_operation_result_size Items returned by successful graph operations.\n",
+        );
+        out.push_str("# TYPE p3m_operation_result_size histogram\n");
+        for ((category, operation), m) in &operations {
+            let name = "p3m_operation_result_size";
+            m.size.render(&mut out, name, &labels(category, operation));
+        }
+
+        out.push_str("# HELP p3m_operation_retries_total Retries of graph operations.\n");
+        out.push_str("# TYPE p3m_operation_retries_total counter\n");
+        for ((category, operation), m) in &operations {
+            let _ = writeln!(
+                out,
+                "p3m_operation_retries_total{{{}}} {}",
+                labels(category, operation),
+                m.retries
+            );
+        }
+
+        out
+    }
+}
+
+/// an operation of a category about to run, see `run`
+pub struct OperationSpan<'a> {
+    category: &'a str,
+    name: &'static str,
+    span: Span,
+}
+
+impl<'a> OperationSpan<'a> {
+    /// `args` are recorded in the span at once, they need not outlive it
+    pub fn new(category: &'a str, name: &'static str, args: &dyn Debug) -> Self {
+        let span = tracing::info_span!(
+            "operation",
+            category,
+            operation = name,
+            args = ?args,
+            size = Empty,
+            retries = Empty,
+            duration_ms = Empty,
+            error = Empty,
+        );
+        OperationSpan {
+            category,
+            name,
+            span,
+        }
+    }
+
+    /// run `fut` within the span, then record its outcome
+    pub async fn run<T: ResultSize>(
+        self,
+        fut: impl Future<Output = Pyo3MongoResult<T>>,
+    ) -> Pyo3MongoResult<T> {
+        let start = Instant::now();
+        let res = fut.instrument(self.span.clone()).await;
+        let duration = start.elapsed();
+
+        let size = res.as_ref().ok().map(ResultSize::result_size);
+        self.span
+            .record("duration_ms", duration.as_secs_f64() * 1000.0);
+        match (&res, size) {
+            (Ok(_), Some(n)) => {
+                self.span.record("size", n);
+            }
+            (Err(e), _) => {
+                self.span.record("error", display(e));
+            }
+            _ => {}
+        }
+        registry().observe(self.category, self.name, duration, size);
+
+        res
+    }
+}
+
+/// log spans (once closed) & events to stderr, filtered by `RUST_LOG` if set,
+/// otherwise by `default_filter` (e.g. "p3m=info"). False if a subscriber is
+/// already installed.
+pub fn init_tracing(default_filter: &str) -> bool {
+    use tracing_subscriber::fmt::format::FmtSpan;
+    use tracing_subscriber::EnvFilter;
+
+    let filter =
+        EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(default_filter));
+    tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_span_events(FmtSpan::CLOSE)
+        .with_writer(std::io::stderr)
+        .try_init()
+        .is_ok()
+}
+
+#[cfg(test)]
+mod test_telemetry {
+    use super::*;
+    use crate::Pyo3MongoError;
+
+    #[test]
+    fn test_histogram() {
+        let mut h = Histogram::new(&[1.0, 10.0]);
+        for v in [0.5, 1.0, 5.0, 50.0] {
+            h.observe(v);
+        }
+        assert_eq!(h.counts, vec![2, 1]);
+        assert_eq!((h.count, h.sum), (4, 56.5));
+
+        let mut out = String::new();
+        h.render(&mut out, "m", "a=\"b\"");
+        assert_eq!(
+            out,
+            "m_bucket{a=\"b\",le=\"1\"} 2\n\
+             m_bucket{a=\"b\",le=\"10\"} 3\n\
+             m_bucket{a=\"b\",le=\"+Inf\"} 4\n\
+             m_sum{a=\"b\"} 56.5\n\
+             m_count{a=\"b\"} 4\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_operation() {
+        // categories are unique, as the registry is shared by tests
+        let category = "test_\"operation\"";
+
+        let res = OperationSpan::new(category, "get_vertexes", &1)
+            .run(async { Ok(vec![1, 2, 3]) })
+            .await;
+        assert_eq!(res.unwrap().len(), 3);
+        let res = OperationSpan::new(category, "get_vertexes", &2)
+            .run(async { Err::<(), _>(Pyo3MongoError::VertexNotFound) })
+            .await;
+        assert!(res.is_err());
+
+        assert_eq!(registry().count(category, "get_vertexes"), (1, 1));
+        registry().retried(category, "get_vertexes");
+        assert_eq!(registry().retries(category, "get_vertexes"), 1);
+
+        let text = render();
+        let labels = "category=\"test_\\\"operation\\\"\",operation=\"get_vertexes\"";
+        assert!(text.contains(&format!(
+            "p3m_operations_total{{{},outcome=\"ok\"}} 1",
+            labels
+        )));
+        assert!(text.contains(&format!(
+            "p3m_operations_total{{{},outcome=\"error\"}} 1",
+            labels
+        )));
+        assert!(text.contains(&format!(
+            "p3m_operation_result_size_bucket{{{},le=\"10\"}} 1",
+            labels
+        )));
+        assert!(text.contains(&format!(
+            "p3m_operation_duration_seconds_count{{{}}} 2",
+            labels
+        )));
+        assert!(text.contains(&format!("p3m_operation_retries_total{{{}}} 1", labels)));
+    }
+}
@@ -0,0 +1,239 @@
+//! Weights
+//!
+//! Aggregate views of `Edge.weight`, computed by MongoDB: `$group` pipelines
+//! over the edges of a category (see `GraphService::out_weights` and friends),
+//! whose results are deserialized into the structs below.
+//!
+//! A missing weight counts as `1`, as in `centrality`.
+
+use std::collections::HashMap;
+
+use mongodb::bson::{doc, oid::ObjectId, Bson, Document};
+use pyo3::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// outgoing weight of a vertex
+#[pyclass(module = "p3m")]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct VertexWeight {
+    #[serde(rename = "_id")]
+    pub vertex: ObjectId,
+    #[pyo3(get)]
+    pub total: f64,
+    /// `0` if there is no outgoing edge
+    #[pyo3(get)]
+    pub average: f64,
+    /// number of outgoing edges
+    #[pyo3(get)]
+    pub count: u64,
+}
+
+impl VertexWeight {
+    /// a vertex without outgoing edges
+    pub fn empty(vertex: ObjectId) -> Self {
+        VertexWeight {
+            vertex,
+            total: 0.0,
+            average: 0.0,
+            count: 0,
+        }
+    }
+}
+
+/// distribution of the weights of edges sharing a label
+#[pyclass(module = "p3m")]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct LabelWeights {
+    /// `None` for unlabeled edges
+    #[serde(rename = "_id")]
+    #[pyo3(get)]
+    pub label: Option<String>,
+    #[pyo3(get)]
+    pub count: u64,
+    #[pyo3(get)]
+    pub total: f64,
+    #[pyo3(get)]
+    pub average: f64,
+    #[pyo3(get)]
+    pub min: f64,
+    #[pyo3(get)]
+    pub max: f64,
+    /// population standard deviation
+    #[pyo3(get)]
+    pub std_dev: f64,
+}
+
+/// weighted adjacency matrix of a set of vertexes
+#[pyclass(module = "p3m")]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct AdjacencyMatrix {
+    /// rows & columns, in order
+    pub vertexes: Vec<ObjectId>,
+    /// `weights[i][j]` sums the weights of edges from `vertexes[i]` to
+    /// `vertexes[j]`, `0` if there is none
+    #[pyo3(get)]
+    pub weights: Vec<Vec<f64>>,
+}
+
+/// total weight of edges from `source` to `target`
+#[derive(Deserialize, Debug)]
+pub struct Cell {
+    pub source: ObjectId,
+    pub target: ObjectId,
+    pub weight: f64,
+}
+
+impl AdjacencyMatrix {
+    /// duplicated vertexes are kept once, cells out of `vertexes` are ignored
+    pub fn new(vertexes: &[ObjectId], cells: impl IntoIterator<Item = Cell>) -> Self {
+        let mut index = HashMap::new();
+        let mut ids = Vec::new();
+        for id in vertexes {
+            index.entry(*id).or_insert_with(|| {
+                ids.push(*id);
+                ids.len() - 1
+            });
+        }
+
+        let mut weights = vec![vec![0.0; ids.len()]; ids.len()];
+        for c in cells {
+            if let (Some(i), Some(j)) = (index.get(&c.source), index.get(&c.target)) {
+                weights[*i][*j] += c.weight;
+            }
+        }
+
+        AdjacencyMatrix {
+            vertexes: ids,
+            weights,
+        }
+    }
+
+    /// `None` if either vertex is out of the matrix
+    pub fn get(&self, source: ObjectId, target: ObjectId) -> Option<f64> {
+        let i = self.vertexes.iter().position(|v| *v == source)?;
+        let j = self.vertexes.iter().position(|v| *v == target)?;
+        Some(self.weights[i][j])
+    }
+}
+
+// weight of an edge, a missing one counts as 1
+fn weight() -> Bson {
+    Bson::Document(doc! {"$ifNull": ["$weight", 1.0]})
+}
+
+/// `VertexWeight`s of the sources of edges matched by `filter`, heaviest first
+pub fn out_weights_pipeline(filter: Document) -> Vec<Document> {
+    vec![
+        doc! {"$match": filter},
+        doc! {"$group": {
+            "_id": "$source",
+            "total": {"$sum": weight()},
+            "average": {"$avg": weight()},
+            "count": {"$sum": 1i64},
+        }},
+        doc! {"$sort": {"total": -1, "_id": 1}},
+    ]
+}
+
+/// `LabelWeights` of edges matched by `filter`, by label
+pub fn label_weights_pipeline(filter: Document) -> Vec<Document> {
+    vec![
+        doc! {"$match": filter},
+        doc! {"$group": {
+            "_id": "$label",
+            "count": {"$sum": 1i64},
+            "total": {"$sum": weight()},
+            "average": {"$avg": weight()},
+            "min": {"$min": weight()},
+            "max": {"$max": weight()},
+            "std_dev": {"$stdDevPop": weight()},
+        }},
+        doc! {"$sort": {"_id": 1}},
+    ]
+}
+
+/// `Cell`s of edges matched by `filter`, parallel edges summed up
+pub fn adjacency_pipeline(filter: Document) -> Vec<Document> {
+    vec![
+        doc! {"$match": filter},
+        doc! {"$group": {
+            "_id": {"source": "$source", "target": "$target"},
+            "weight": {"$sum": weight()},
+        }},
+        doc! {"$project": {
+            "_id": 0,
+            "source": "$_id.source",
+            "target": "$_id.target",
+            "weight": 1,
+        }},
+    ]
+}
+
+#[cfg(test)]
+mod test_weights {
+    use mongodb::bson;
+
+    use super::*;
+
+    #[test]
+    fn test_adjacency_matrix() {
+        let (a, b, c) = (ObjectId::new(), ObjectId::new(), ObjectId::new());
+        let cell = |source, target, weight| Cell {
+            source,
+            target,
+            weight,
+        };
+        let matrix = AdjacencyMatrix::new(
+            &[a, b, a],
+            vec![cell(a, b, 2.0), cell(b, a, 1.0), cell(a, c, 5.0)],
+        );
+
+        assert_eq!(matrix.vertexes, vec![a, b]);
+        assert_eq!(matrix.weights, vec![vec![0.0, 2.0], vec![1.0, 0.0]]);
+        assert_eq!(matrix.get(a, b), Some(2.0));
+        assert_eq!(matrix.get(a, c), None);
+    }
+
+    #[test]
+    fn test_pipelines() {
+        let filter = doc! {"label": "knows"};
+        let pipeline = out_weights_pipeline(filter.clone());
+        assert_eq!(pipeline[0], doc! {"$match": {"label": "knows"}});
+        let group = pipeline[1].get_document("$group").unwrap();
+        assert_eq!(group.get_str("_id").unwrap(), "$source");
+
+        let pipeline = label_weights_pipeline(filter.clone());
+        let group = pipeline[1].get_document("$group").unwrap();
+        assert_eq!(
+            group.get_document("std_dev").unwrap(),
+            &doc! {"$stdDevPop": {"$ifNull": ["$weight", 1.0]}}
+        );
+
+        let pipeline = adjacency_pipeline(filter);
+        assert!(pipeline[2].contains_key("$project"));
+    }
+
+    #[test]
+    fn test_deserialize() {
+        // as returned by `$group`: integral sums may be integers
+        let id = ObjectId::new();
+        let doc = doc! {"_id": id, "total": 3i32, "average": 1.5, "count": 2i64};
+        let weight: VertexWeight = bson::from_document(doc).unwrap();
+        assert_eq!(
+            weight,
+            VertexWeight {
+                vertex: id,
+                total: 3.0,
+                average: 1.5,
+                count: 2,
+            }
+        );
+
+        let doc = doc! {
+            "_id": null, "count": 1i64, "total": 1.0, "average": 1.0,
+            "min": 1.0, "max": 1.0, "std_dev": 0.0,
+        };
+        let weights: LabelWeights = bson::from_document(doc).unwrap();
+        assert_eq!(weights.label, None);
+    }
+}